@@ -0,0 +1,672 @@
+//! Interface en ligne de commande de NTK Ultra-Compression
+
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ntk_core::{
+    Chunking, Codec, CodecPolicy, CompressionError, CompressionOptions, Compressor, GapFill, KeyDerivation,
+    OverwritePolicy, SteganographyError,
+};
+use serde_json::json;
+
+// Codes de sortie, un par type de `CompressionError`
+const EXIT_FAILURE: u8 = 1;
+const EXIT_INVALID_FORMAT: u8 = 3;
+const EXIT_ENCRYPTION: u8 = 4;
+const EXIT_AUTHENTICATION: u8 = 5;
+const EXIT_INTEGRITY: u8 = 6;
+const EXIT_IO: u8 = 7;
+const EXIT_COMPRESSION: u8 = 8;
+const EXIT_STEGANOGRAPHY: u8 = 9;
+const EXIT_CANCELLED: u8 = 10;
+
+#[derive(Parser)]
+#[command(name = "ntk", version, about = "NTK Ultra-Compression")]
+struct Cli {
+    /// Affiche les résultats et les erreurs en JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Comportement si la sortie existe déjà
+    #[arg(long, global = true, value_enum, default_value = "overwrite")]
+    overwrite: OverwriteArg,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compresse un fichier ou un dossier
+    Compress {
+        input: PathBuf,
+        output: PathBuf,
+        #[command(flatten)]
+        options: OptionArgs,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Décompresse une archive
+    Decompress {
+        input: PathBuf,
+        output: PathBuf,
+        /// Dictionnaire zstd externe utilisé par l'archive
+        #[arg(long)]
+        dictionary: Option<PathBuf>,
+        /// Archive de base, si elle n'est plus à côté de l'archive incrémentale
+        #[arg(long)]
+        base: Option<PathBuf>,
+        /// Récupère ce qui peut l'être d'une archive endommagée (plages perdues remplies de zéros)
+        #[arg(long)]
+        salvage: bool,
+        /// Avec --salvage : omet les plages perdues au lieu de les remplir
+        #[arg(long, requires = "salvage")]
+        omit_gaps: bool,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Affiche les métadonnées d'une archive
+    Info {
+        archive: PathBuf,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Vérifie une archive sans rien écrire
+    Verify {
+        archive: PathBuf,
+        /// Dictionnaire zstd externe utilisé par l'archive
+        #[arg(long)]
+        dictionary: Option<PathBuf>,
+        /// Archive de base, si elle n'est plus à côté de l'archive incrémentale
+        #[arg(long)]
+        base: Option<PathBuf>,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Réécrit une archive incrémentale et sa chaîne de bases en une archive autonome
+    Consolidate {
+        archive: PathBuf,
+        output: PathBuf,
+        /// Archive de base, si elle n'est plus à côté de l'archive incrémentale
+        #[arg(long)]
+        base: Option<PathBuf>,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Reconstruit les fragments endommagés d'une archive protégée par parité
+    Repair {
+        archive: PathBuf,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Cache une archive dans une image PNG, un JPEG (coefficients DCT) ou un fichier WAV,
+    /// selon l'extension
+    Hide {
+        archive: PathBuf,
+        cover: PathBuf,
+        output: PathBuf,
+        /// Bits de poids faible utilisés par canal de couleur ou échantillon audio
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+        bits: u8,
+        /// Le mot de passe détermine l'ordre des emplacements utilisés
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Extrait une archive cachée dans une image, un JPEG ou un fichier WAV
+    Extract {
+        cover: PathBuf,
+        output: PathBuf,
+        /// Mot de passe utilisé pour cacher l'archive
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+}
+
+/// Un drapeau par champ de `CompressionOptions`
+#[derive(Args)]
+struct OptionArgs {
+    /// Niveau de compression (interprété par le codec)
+    #[arg(short, long)]
+    level: Option<u32>,
+    /// Codec des blocs
+    #[arg(long, value_enum)]
+    codec: Option<CodecArg>,
+    /// Compromis vitesse / taux du codec automatique
+    #[arg(long, value_enum)]
+    codec_policy: Option<PolicyArg>,
+    /// Taille des blocs en octets
+    #[arg(long)]
+    block_size: Option<usize>,
+    /// Découpage des blocs (cdc : selon le contenu, avec déduplication)
+    #[arg(long, value_enum)]
+    chunking: Option<ChunkingArg>,
+    /// Nombre de threads
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Nombre maximal de blocs en mémoire (0 : nombre de threads)
+    #[arg(long)]
+    in_flight_blocks: Option<usize>,
+    /// Redondance de la parité en pourcentage (0 : aucune)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    parity: Option<u8>,
+    /// Découpe l'archive en volumes de cette taille au plus (suffixes K, M, G : 4G, 700M)
+    #[arg(long, value_parser = parse_size)]
+    volume_size: Option<u64>,
+    /// Taille maximale d'un dictionnaire entraîné
+    #[arg(long)]
+    dictionary_size: Option<usize>,
+    /// Dictionnaire zstd à utiliser
+    #[arg(long)]
+    dictionary: Option<PathBuf>,
+    /// Archive de base : ne stocke que ce qui n'y figure pas (archive incrémentale)
+    #[arg(long)]
+    base: Option<PathBuf>,
+    /// N'enregistre que l'identifiant du dictionnaire, pas son contenu
+    #[arg(long, requires = "dictionary")]
+    external_dictionary: bool,
+    /// Chiffre l'archive (mot de passe demandé si aucune source n'est donnée)
+    #[arg(short, long)]
+    encrypt: bool,
+    /// Fonction de dérivation de clé
+    #[arg(long, value_enum)]
+    kdf: Option<KdfArg>,
+    /// Argon2id : mémoire en Kio
+    #[arg(long)]
+    kdf_memory: Option<u32>,
+    /// Argon2id / PBKDF2 : nombre d'itérations
+    #[arg(long)]
+    kdf_iterations: Option<u32>,
+    /// Argon2id : degré de parallélisme
+    #[arg(long)]
+    kdf_parallelism: Option<u32>,
+    /// scrypt : log2(N)
+    #[arg(long)]
+    scrypt_log_n: Option<u8>,
+    /// scrypt : paramètre r
+    #[arg(long)]
+    scrypt_r: Option<u32>,
+    /// scrypt : paramètre p
+    #[arg(long)]
+    scrypt_p: Option<u32>,
+    /// Image PNG dans laquelle cacher l'archive
+    #[arg(long)]
+    steganography_image: Option<PathBuf>,
+    /// Bits de poids faible utilisés par canal de couleur (1 ou 2)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2))]
+    steganography_bits: Option<u8>,
+}
+
+/// Sources possibles du mot de passe ; à défaut, il est demandé au terminal
+#[derive(Args)]
+struct PasswordArgs {
+    /// Variable d'environnement contenant le mot de passe
+    #[arg(long, value_name = "VAR", conflicts_with = "password_file")]
+    password_env: Option<String>,
+    /// Fichier contenant le mot de passe (première ligne)
+    #[arg(long, value_name = "PATH")]
+    password_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CodecArg {
+    Zstd,
+    Lz4,
+    Brotli,
+    Stored,
+    Auto,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PolicyArg {
+    Speed,
+    Balanced,
+    Ratio,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ChunkingArg {
+    Fixed,
+    Cdc,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OverwriteArg {
+    Fail,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum KdfArg {
+    Argon2id,
+    Scrypt,
+    Pbkdf2,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.json;
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let code = exit_code(&e);
+            if json {
+                println!("{}", json!({ "error": format!("{:#}", e), "exit_code": code }));
+            } else {
+                eprintln!("ntk: {:#}", e);
+            }
+            ExitCode::from(code)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let json = cli.json;
+    let overwrite = OverwritePolicy::from(cli.overwrite);
+
+    match cli.command {
+        Command::Compress { input, output, options, password } => {
+            // Avant la saisie du mot de passe et la compression
+            if options.steganography_image.is_some() {
+                Compressor::check_image_output(&output)?;
+            }
+            let options = options.into_options(&password, overwrite)?;
+            let mut compressor = Compressor::new(options);
+            show_progress(&mut compressor, json);
+            let metadata = compressor.compress(&input, &output)?;
+            finish_progress(json);
+
+            if json {
+                println!("{}", serde_json::to_string(&metadata)?);
+            } else {
+                println!(
+                    "{} -> {} ({} -> {} bytes, ratio {:.2})",
+                    input.display(), metadata.output_path.as_deref().unwrap_or_default(),
+                    metadata.original_size, metadata.compressed_size, metadata.compression_ratio
+                );
+            }
+        }
+        Command::Decompress { input, output, dictionary, base, salvage: true, omit_gaps, password, .. } => {
+            let compressor = reader_compressor(&input, dictionary, base, &password, true, overwrite)?;
+            let fill = if omit_gaps { GapFill::Omit } else { GapFill::Zeros };
+            let report = compressor.salvage(&input, &output, fill)?;
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else {
+                println!("{} -> {}: {} block(s), {} byte(s) recovered",
+                    input.display(), report.output_path, report.recovered_blocks, report.recovered_bytes);
+                for range in &report.lost_ranges {
+                    println!("lost: {}..{}", range.start, range.end);
+                }
+            }
+        }
+        Command::Decompress { input, output, dictionary, base, password, .. } => {
+            let mut compressor = reader_compressor(&input, dictionary, base, &password, true, overwrite)?;
+            show_progress(&mut compressor, json);
+            let output = compressor.decompress(&input, &output)?;
+            finish_progress(json);
+            report_ok(json, &format!("{} -> {}", input.display(), output.display()));
+        }
+        Command::Info { archive, password } => {
+            let compressor = reader_compressor(&archive, None, None, &password, false, overwrite)?;
+            let metadata = compressor.get_metadata(&archive)?;
+
+            if json {
+                println!("{}", serde_json::to_string(&metadata)?);
+            } else {
+                println!("name:            {}", metadata.original_name);
+                println!("original size:   {}", metadata.original_size);
+                println!("compressed size: {}", metadata.compressed_size);
+                println!("ratio:           {:.2}", metadata.compression_ratio);
+                println!("encrypted:       {}", metadata.encrypted);
+                if !metadata.checksum.is_empty() {
+                    println!("checksum:        {}", metadata.checksum);
+                }
+            }
+        }
+        Command::Verify { archive, dictionary, base, password } => {
+            let mut compressor = reader_compressor(&archive, dictionary, base, &password, true, overwrite)?;
+            show_progress(&mut compressor, json);
+            compressor.verify(&archive)?;
+            finish_progress(json);
+            report_ok(json, &format!("{}: OK", archive.display()));
+        }
+        Command::Consolidate { archive, output, base, password } => {
+            let mut compressor = reader_compressor(&archive, None, base, &password, true, overwrite)?;
+            show_progress(&mut compressor, json);
+            let metadata = compressor.consolidate(&archive, &output)?;
+            finish_progress(json);
+
+            if json {
+                println!("{}", serde_json::to_string(&metadata)?);
+            } else {
+                println!(
+                    "{} -> {} ({} -> {} bytes)",
+                    archive.display(), metadata.output_path.as_deref().unwrap_or_default(),
+                    metadata.original_size, metadata.compressed_size
+                );
+            }
+        }
+        Command::Repair { archive, password } => {
+            let compressor = reader_compressor(&archive, None, None, &password, true, overwrite)?;
+            let report = compressor.repair(&archive)?;
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else if report.is_empty() {
+                println!("{}: OK", archive.display());
+            } else {
+                println!("{}: repaired {} block(s), {} parity shard(s)",
+                    archive.display(), report.repaired_blocks.len(), report.repaired_parity_shards);
+            }
+        }
+        Command::Hide { archive, cover, output, bits, password } => {
+            if is_jpeg(&cover) {
+                Compressor::check_jpeg_output(&output)?;
+            } else if !is_audio(&cover) {
+                Compressor::check_image_output(&output)?;
+            }
+            let compressor = stego_compressor(bits, &password, overwrite)?;
+            let output = if is_audio(&cover) {
+                compressor.hide_in_audio(&archive, &cover, &output)?
+            } else if is_jpeg(&cover) {
+                compressor.hide_in_jpeg(&archive, &cover, &output)?
+            } else {
+                compressor.hide_in_image(&archive, &cover, &output)?
+            };
+            report_ok(json, &format!("{} -> {}", archive.display(), output.display()));
+        }
+        Command::Extract { cover, output, password } => {
+            let compressor = stego_compressor(0, &password, overwrite)?;
+            let output = if is_audio(&cover) {
+                compressor.extract_from_audio(&cover, &output)?
+            } else if is_jpeg(&cover) {
+                compressor.extract_from_jpeg(&cover, &output)?
+            } else {
+                compressor.extract_from_image(&cover, &output)?
+            };
+            report_ok(json, &format!("{} -> {}", cover.display(), output.display()));
+        }
+    }
+    Ok(())
+}
+
+impl OptionArgs {
+    fn into_options(self, password: &PasswordArgs, overwrite: OverwritePolicy) -> Result<CompressionOptions> {
+        let defaults = CompressionOptions::default();
+        let use_encryption = self.encrypt || password.is_given();
+        let key_derivation = self.key_derivation();
+
+        Ok(CompressionOptions {
+            level: self.level.unwrap_or(defaults.level),
+            codec: self.codec.map(Codec::from).unwrap_or(defaults.codec),
+            codec_policy: self.codec_policy.map(CodecPolicy::from).unwrap_or(defaults.codec_policy),
+            block_size: self.block_size.unwrap_or(defaults.block_size),
+            chunking: self.chunking.map(Chunking::from).unwrap_or(defaults.chunking),
+            threads: self.threads.unwrap_or(defaults.threads),
+            in_flight_blocks: self.in_flight_blocks.unwrap_or(defaults.in_flight_blocks),
+            overwrite,
+            parity_percent: self.parity.unwrap_or(defaults.parity_percent),
+            volume_size: self.volume_size.unwrap_or(defaults.volume_size),
+            dictionary_size: self.dictionary_size.unwrap_or(defaults.dictionary_size),
+            dictionary_path: self.dictionary.map(|p| p.to_string_lossy().into_owned()),
+            external_dictionary: self.external_dictionary,
+            base_archive: self.base.map(|p| p.to_string_lossy().into_owned()),
+            use_encryption,
+            password: if use_encryption { Some(password.read(true)?) } else { None },
+            key_derivation,
+            use_steganography: self.steganography_image.is_some(),
+            steganography_image: self.steganography_image.map(|p| p.to_string_lossy().into_owned()),
+            steganography_bits: self.steganography_bits.unwrap_or(defaults.steganography_bits),
+        })
+    }
+
+    /// Paramètres par défaut de la KDF choisie, remplacés par ceux donnés
+    fn key_derivation(&self) -> KeyDerivation {
+        let default = KeyDerivation::default();
+        match self.kdf.unwrap_or(KdfArg::Argon2id) {
+            KdfArg::Argon2id => {
+                let KeyDerivation::Argon2id { memory_kib, iterations, parallelism } = default else {
+                    unreachable!("la KDF par défaut est Argon2id")
+                };
+                KeyDerivation::Argon2id {
+                    memory_kib: self.kdf_memory.unwrap_or(memory_kib),
+                    iterations: self.kdf_iterations.unwrap_or(iterations),
+                    parallelism: self.kdf_parallelism.unwrap_or(parallelism),
+                }
+            }
+            KdfArg::Scrypt => KeyDerivation::Scrypt {
+                log_n: self.scrypt_log_n.unwrap_or(17),
+                r: self.scrypt_r.unwrap_or(8),
+                p: self.scrypt_p.unwrap_or(1),
+            },
+            KdfArg::Pbkdf2 => KeyDerivation::Pbkdf2 {
+                iterations: self.kdf_iterations.unwrap_or(600_000),
+            },
+        }
+    }
+}
+
+impl PasswordArgs {
+    fn is_given(&self) -> bool {
+        self.password_env.is_some() || self.password_file.is_some()
+    }
+
+    /// Lit le mot de passe depuis la source indiquée, ou le demande (avec confirmation)
+    fn read(&self, confirm: bool) -> Result<String> {
+        if let Some(var) = &self.password_env {
+            return std::env::var(var).with_context(|| format!("environment variable {} is not set", var));
+        }
+        if let Some(path) = &self.password_file {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("cannot read password file {}", path.display()))?;
+            return Ok(content.lines().next().unwrap_or_default().to_string());
+        }
+
+        let password = rpassword::prompt_password("Password: ")?;
+        if confirm && rpassword::prompt_password("Confirm password: ")? != password {
+            bail!("passwords do not match");
+        }
+        Ok(password)
+    }
+}
+
+/// Support audio (WAV) plutôt qu'image, d'après l'extension
+fn is_audio(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("wav"))
+}
+
+/// JPEG : insertion dans les coefficients DCT plutôt que dans les pixels
+fn is_jpeg(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("jpg") || e.eq_ignore_ascii_case("jpeg"))
+}
+
+/// Compresseur de stéganographie ; le mot de passe n'est lu que si une source est donnée
+fn stego_compressor(bits: u8, password: &PasswordArgs, overwrite: OverwritePolicy) -> Result<Compressor> {
+    let password = if password.is_given() { Some(password.read(false)?) } else { None };
+    Ok(Compressor::new(CompressionOptions {
+        steganography_bits: bits,
+        password,
+        overwrite,
+        ..Default::default()
+    }))
+}
+
+/// Compresseur de lecture ; `prompt` : demander le mot de passe d'une archive chiffrée
+/// si aucune source n'est donnée
+fn reader_compressor(
+    archive: &Path,
+    dictionary: Option<PathBuf>,
+    base: Option<PathBuf>,
+    password: &PasswordArgs,
+    prompt: bool,
+    overwrite: OverwritePolicy,
+) -> Result<Compressor> {
+    // Une archive cachée avec un mot de passe ne se trouve pas sans lui
+    let encrypted = prompt && match Compressor::new(CompressionOptions::default()).get_metadata(archive) {
+        Ok(metadata) => metadata.encrypted,
+        Err(e) if matches!(
+            e.downcast_ref::<CompressionError>(),
+            Some(CompressionError::SteganographyError(SteganographyError::NoHiddenData))
+        ) => true,
+        Err(e) => return Err(e),
+    };
+    let password = if encrypted || password.is_given() { Some(password.read(false)?) } else { None };
+
+    Ok(Compressor::new(CompressionOptions {
+        use_encryption: password.is_some(),
+        password,
+        dictionary_path: dictionary.map(|p| p.to_string_lossy().into_owned()),
+        base_archive: base.map(|p| p.to_string_lossy().into_owned()),
+        overwrite,
+        ..Default::default()
+    }))
+}
+
+/// Progression sur la sortie d'erreur, seulement dans un terminal et hors mode JSON
+fn show_progress(compressor: &mut Compressor, json: bool) {
+    if json || !io::stderr().is_terminal() {
+        return;
+    }
+    compressor.set_progress_callback(|info| {
+        let percent = if info.total_bytes > 0 {
+            info.processed_bytes as f64 / info.total_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+        eprint!("\r{:5.1}%  {:8.2} MB/s", percent.min(100.0), info.current_speed / (1024.0 * 1024.0));
+        let _ = io::stderr().flush();
+    });
+}
+
+fn finish_progress(json: bool) {
+    if !json && io::stderr().is_terminal() {
+        eprintln!();
+    }
+}
+
+fn report_ok(json: bool, message: &str) {
+    if json {
+        println!("{}", json!({ "status": "ok" }));
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Taille en octets, avec un suffixe binaire optionnel (K, M, G, T)
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, shift) = match value.char_indices().last() {
+        Some((i, unit)) if unit.is_ascii_alphabetic() => {
+            let shift = match unit.to_ascii_uppercase() {
+                'K' => 10,
+                'M' => 20,
+                'G' => 30,
+                'T' => 40,
+                _ => return Err(format!("unknown size unit '{}'", unit)),
+            };
+            (&value[..i], shift)
+        }
+        _ => (value, 0),
+    };
+    let number: u64 = number.trim().parse().map_err(|_| format!("invalid size '{}'", value))?;
+    number.checked_mul(1 << shift).ok_or_else(|| format!("size '{}' is too large", value))
+}
+
+fn exit_code(e: &anyhow::Error) -> u8 {
+    if let Some(e) = e.downcast_ref::<CompressionError>() {
+        return match e {
+            CompressionError::InvalidFormat => EXIT_INVALID_FORMAT,
+            CompressionError::EncryptionError(_) => EXIT_ENCRYPTION,
+            CompressionError::AuthenticationError(_) => EXIT_AUTHENTICATION,
+            CompressionError::IntegrityError(_) => EXIT_INTEGRITY,
+            CompressionError::IoError(e) if is_steganography(e) => EXIT_STEGANOGRAPHY,
+            CompressionError::IoError(_) => EXIT_IO,
+            CompressionError::CompressionError(_) => EXIT_COMPRESSION,
+            CompressionError::SteganographyError(_) => EXIT_STEGANOGRAPHY,
+            CompressionError::Cancelled => EXIT_CANCELLED,
+        };
+    }
+    if let Some(e) = e.downcast_ref::<io::Error>() {
+        return if is_steganography(e) { EXIT_STEGANOGRAPHY } else { EXIT_IO };
+    }
+    EXIT_FAILURE
+}
+
+/// Erreur d'écriture levée par l'image de sortie (capacité dépassée)
+fn is_steganography(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<SteganographyError>())
+}
+
+impl From<CodecArg> for Codec {
+    fn from(codec: CodecArg) -> Self {
+        match codec {
+            CodecArg::Zstd => Codec::Zstd,
+            CodecArg::Lz4 => Codec::Lz4,
+            CodecArg::Brotli => Codec::Brotli,
+            CodecArg::Stored => Codec::Stored,
+            CodecArg::Auto => Codec::Auto,
+        }
+    }
+}
+
+impl From<ChunkingArg> for Chunking {
+    fn from(chunking: ChunkingArg) -> Self {
+        match chunking {
+            ChunkingArg::Fixed => Chunking::Fixed,
+            ChunkingArg::Cdc => Chunking::ContentDefined,
+        }
+    }
+}
+
+impl From<OverwriteArg> for OverwritePolicy {
+    fn from(policy: OverwriteArg) -> Self {
+        match policy {
+            OverwriteArg::Fail => OverwritePolicy::Fail,
+            OverwriteArg::Overwrite => OverwritePolicy::Overwrite,
+            OverwriteArg::Rename => OverwritePolicy::Rename,
+        }
+    }
+}
+
+impl From<PolicyArg> for CodecPolicy {
+    fn from(policy: PolicyArg) -> Self {
+        match policy {
+            PolicyArg::Speed => CodecPolicy::Speed,
+            PolicyArg::Balanced => CodecPolicy::Balanced,
+            PolicyArg::Ratio => CodecPolicy::Ratio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let error: anyhow::Error = CompressionError::AuthenticationError("archive is truncated".into()).into();
+        assert_eq!(exit_code(&error), EXIT_AUTHENTICATION);
+        let error: anyhow::Error = io::Error::from(io::ErrorKind::NotFound).into();
+        assert_eq!(exit_code(&error), EXIT_IO);
+        assert_eq!(exit_code(&anyhow::anyhow!("passwords do not match")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("700M"), Ok(700 << 20));
+        assert_eq!(parse_size("4g"), Ok(4 << 30));
+        assert!(parse_size("4X").is_err());
+        assert!(parse_size("G").is_err());
+    }
+}
@@ -0,0 +1,796 @@
+//! Archives multi-fichiers : parcours de dossiers, table des matières et extraction
+//!
+//! Le flux décompressé d'une archive multi-fichiers est constitué de la longueur de la
+//! table des matières (u64 LE), de la table elle-même (JSON) puis du contenu des
+//! fichiers, concaténés dans l'ordre de la table.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, Write};
+use std::ops::ControlFlow;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::output::{AtomicFile, OverwritePolicy};
+use crate::{file_name, CompressionError, Compressor, FileMetadata, FrameWriter, ParsedHeader};
+
+// Taille maximale acceptée pour la table des matières (protection contre un en-tête forgé)
+const MAX_TOC_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+/// Entrée de la table des matières d'une archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// Chemin relatif, composants séparés par '/'
+    pub path: String,
+    pub kind: EntryKind,
+    /// Taille du contenu (fichiers uniquement)
+    pub size: u64,
+    /// Permissions Unix
+    pub mode: u32,
+    /// Date de modification (secondes depuis l'epoch)
+    pub mtime: u64,
+    /// Cible du lien symbolique
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<String>,
+    /// Position du contenu dans la zone de données
+    pub offset: u64,
+}
+
+impl Compressor {
+    /// Crée une archive contenant les fichiers et dossiers donnés (parcourus récursivement)
+    pub fn compress_entries<P: AsRef<Path>, Q: AsRef<Path>>(&self, inputs: &[P], output: Q) -> Result<FileMetadata> {
+        let start = Instant::now();
+        let output_path = output.as_ref();
+
+        let mut entries = Vec::new();
+        let mut sources = Vec::new();
+        let mut seen = HashSet::new();
+        let mut data_size = 0u64;
+        for input in inputs {
+            let input = input.as_ref();
+            let name = file_name(input)?;
+            if !seen.insert(name.clone()) {
+                return Err(CompressionError::IoError(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Duplicate entry name: {}", name)
+                )).into());
+            }
+            collect_entries(input, name, &mut entries, &mut sources, &mut data_size)?;
+        }
+
+        let archive_name = match inputs {
+            [single] => file_name(single.as_ref())?,
+            _ => output_path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+
+        // Flux logique : longueur de la table, table, puis contenu des fichiers
+        let toc = serde_json::to_vec(&entries)?;
+        let mut prefix = (toc.len() as u64).to_le_bytes().to_vec();
+        prefix.extend_from_slice(&toc);
+        let total_size = prefix.len() as u64 + data_size;
+        // Le découpage selon le contenu s'arrête aux limites de chaque fichier
+        let segments: Vec<u64> = std::iter::once(prefix.len() as u64)
+            .chain(sources.iter().map(|(_, size)| *size))
+            .collect();
+
+        let (key, salt, nonce_prefix) = if self.options.use_encryption {
+            self.prepare_encryption()?
+        } else {
+            (vec![], vec![], vec![])
+        };
+
+        let mut header = self.new_header(&archive_name, total_size)?;
+        header.multi_entry = true;
+        let header_bytes = self.encode_header(&header, &salt, &nonce_prefix)?;
+
+        let mut output_file = self.create_output(output_path)?;
+        output_file.write_all(&header_bytes)?;
+
+        let mut frames = FrameWriter::new(output_file, header_bytes.len() as u64, self.options.parity_percent)?;
+        let mut reader = Cursor::new(prefix).chain(SourceReader::new(sources));
+        self.compress_stream(&mut reader, &segments, &header_bytes, &key, &nonce_prefix, &mut frames)?;
+
+        let footer = frames.end_blocks()?;
+        let compressed_size = frames.finish(&self.seal_footer(&footer, &header_bytes, &key, &nonce_prefix)?)?;
+        let output_path = frames.into_inner().commit()?;
+        Ok(FileMetadata {
+            original_name: archive_name,
+            original_size: total_size,
+            compressed_size,
+            compression_ratio: total_size as f64 / compressed_size as f64,
+            encrypted: self.options.use_encryption,
+            creation_time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs(),
+            checksum: footer.checksum.unwrap_or_default(),
+            estimated_time: start.elapsed().as_secs_f64(),
+            output_path: Some(output_path.to_string_lossy().into_owned()),
+        })
+    }
+
+    /// Liste les entrées d'une archive ; une archive mono-fichier contient une seule entrée
+    pub fn list_entries<P: AsRef<Path>>(&self, archive: P) -> Result<Vec<ArchiveEntry>> {
+        let (mut input, parsed) = self.open_archive(archive.as_ref())?;
+        if !parsed.header.multi_entry {
+            return Ok(vec![single_entry(&parsed)]);
+        }
+
+        // Seuls les premiers blocs, qui contiennent la table, sont décompressés
+        let mut toc = TocParser::default();
+        self.decode_blocks(&parsed, &mut input, |_, data| {
+            Ok(match toc.feed(data)? {
+                Some(_) => ControlFlow::Break(()),
+                None => ControlFlow::Continue(()),
+            })
+        })?;
+        toc.entries.ok_or_else(|| CompressionError::InvalidFormat.into())
+    }
+
+    /// Extrait toutes les entrées dans `output_dir`
+    pub fn extract_all<P: AsRef<Path>>(&self, archive: P, output_dir: P) -> Result<Vec<ArchiveEntry>> {
+        self.extract_entries(archive, output_dir, &[])
+    }
+
+    /// Extrait les entrées sélectionnées (chemin exact ou dossier parent) dans `output_dir`.
+    /// Une sélection vide extrait tout.
+    pub fn extract_entries<P: AsRef<Path>>(&self, archive: P, output_dir: P, selected: &[String]) -> Result<Vec<ArchiveEntry>> {
+        let (mut input, parsed) = self.open_archive(archive.as_ref())?;
+        self.extract_into(&parsed, &mut input, output_dir.as_ref(), selected)
+    }
+
+    pub(crate) fn extract_into<R: Read + Seek>(
+        &self,
+        parsed: &ParsedHeader,
+        input: &mut R,
+        output_dir: &Path,
+        selected: &[String],
+    ) -> Result<Vec<ArchiveEntry>> {
+        fs::create_dir_all(output_dir)?;
+
+        let mut extractor = EntryExtractor::new(output_dir, selected, self.options.overwrite);
+        if !parsed.header.multi_entry {
+            extractor.start(vec![single_entry(parsed)])?;
+        }
+
+        // En cas d'erreur, le fichier en cours est abandonné ; ceux déjà extraits sont complets
+        self.decode_blocks(parsed, input, |_, data| {
+            extractor.feed(data)?;
+            Ok(ControlFlow::Continue(()))
+        })?;
+        extractor.finish()
+    }
+}
+
+/// Entrée implicite d'une archive mono-fichier
+fn single_entry(parsed: &ParsedHeader) -> ArchiveEntry {
+    ArchiveEntry {
+        path: parsed.header.original_name.clone(),
+        kind: EntryKind::File,
+        size: parsed.header.original_size,
+        mode: 0o644,
+        mtime: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        symlink_target: None,
+        offset: 0,
+    }
+}
+
+/// Parcourt `path` récursivement (sans suivre les liens) et ajoute ses entrées
+fn collect_entries(
+    path: &Path,
+    name: String,
+    entries: &mut Vec<ArchiveEntry>,
+    sources: &mut Vec<(PathBuf, u64)>,
+    data_size: &mut u64,
+) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    let file_type = metadata.file_type();
+    let mtime = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut entry = ArchiveEntry {
+        path: name,
+        kind: EntryKind::File,
+        size: 0,
+        mode: file_mode(&metadata),
+        mtime,
+        symlink_target: None,
+        offset: *data_size,
+    };
+
+    if file_type.is_symlink() {
+        entry.kind = EntryKind::Symlink;
+        entry.symlink_target = Some(fs::read_link(path)?.to_string_lossy().replace('\\', "/"));
+        entries.push(entry);
+    } else if file_type.is_dir() {
+        entry.kind = EntryKind::Directory;
+        let prefix = entry.path.clone();
+        entries.push(entry);
+
+        // Ordre alphabétique pour une archive reproductible
+        let mut children: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            let child_name = format!("{}/{}", prefix, child.file_name().to_string_lossy());
+            collect_entries(&child.path(), child_name, entries, sources, data_size)?;
+        }
+    } else if file_type.is_file() {
+        entry.size = metadata.len();
+        *data_size += metadata.len();
+        sources.push((path.to_path_buf(), metadata.len()));
+        entries.push(entry);
+    }
+    // Les autres types (sockets, périphériques, ...) sont ignorés
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &fs::Metadata) -> u32 {
+    if metadata.is_dir() {
+        0o755
+    } else if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+/// Lit le contenu des fichiers sources à la suite, chacun limité à sa taille enregistrée
+struct SourceReader {
+    sources: std::vec::IntoIter<(PathBuf, u64)>,
+    current: Option<(PathBuf, io::Take<File>)>,
+}
+
+impl SourceReader {
+    fn new(sources: Vec<(PathBuf, u64)>) -> Self {
+        Self { sources: sources.into_iter(), current: None }
+    }
+}
+
+impl Read for SourceReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some((path, reader)) = &mut self.current {
+                let n = reader.read(buf)?;
+                if n > 0 || buf.is_empty() {
+                    return Ok(n);
+                }
+                // La table des matières est déjà écrite : un fichier raccourci la rendrait fausse
+                if reader.limit() > 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("{} changed during compression", path.display())
+                    ));
+                }
+                self.current = None;
+            }
+
+            match self.sources.next() {
+                Some((path, size)) => {
+                    let file = File::open(&path)?;
+                    self.current = Some((path, file.take(size)));
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Lit la table des matières en tête du flux décompressé
+#[derive(Default)]
+struct TocParser {
+    buffer: Vec<u8>,
+    entries: Option<Vec<ArchiveEntry>>,
+}
+
+impl TocParser {
+    /// Consomme des données ; une fois la table lue, renvoie le reste des données
+    fn feed<'a>(&mut self, data: &'a [u8]) -> Result<Option<&'a [u8]>> {
+        if self.entries.is_some() {
+            return Ok(Some(data));
+        }
+
+        let mut needed = 8usize;
+        if self.buffer.len() >= 8 {
+            let toc_len = u64::from_le_bytes(self.buffer[..8].try_into().unwrap());
+            if toc_len > MAX_TOC_SIZE {
+                return Err(CompressionError::InvalidFormat.into());
+            }
+            needed += toc_len as usize;
+        }
+
+        let mut consumed = 0;
+        while self.buffer.len() < needed && consumed < data.len() {
+            let take = (needed - self.buffer.len()).min(data.len() - consumed);
+            self.buffer.extend_from_slice(&data[consumed..consumed + take]);
+            consumed += take;
+
+            // Longueur connue : on sait désormais jusqu'où lire
+            if needed == 8 && self.buffer.len() == 8 {
+                let toc_len = u64::from_le_bytes(self.buffer[..8].try_into().unwrap());
+                if toc_len > MAX_TOC_SIZE {
+                    return Err(CompressionError::InvalidFormat.into());
+                }
+                needed += toc_len as usize;
+            }
+        }
+
+        if self.buffer.len() < needed {
+            return Ok(None);
+        }
+
+        let entries: Vec<ArchiveEntry> = serde_json::from_slice(&self.buffer[8..])
+            .map_err(|_| CompressionError::InvalidFormat)?;
+        self.entries = Some(entries);
+        self.buffer = Vec::new();
+        Ok(Some(&data[consumed..]))
+    }
+}
+
+/// Reconstruit les entrées à partir du flux décompressé
+struct EntryExtractor<'a> {
+    root: &'a Path,
+    selected: &'a [String],
+    overwrite: OverwritePolicy,
+    toc: TocParser,
+    /// Fichiers à recevoir, dans l'ordre du flux
+    pending: std::vec::IntoIter<ArchiveEntry>,
+    current: Option<(ArchiveEntry, Option<AtomicFile>, u64)>,
+    entries: Vec<ArchiveEntry>,
+}
+
+impl<'a> EntryExtractor<'a> {
+    fn new(root: &'a Path, selected: &'a [String], overwrite: OverwritePolicy) -> Self {
+        Self {
+            root,
+            selected,
+            overwrite,
+            toc: TocParser::default(),
+            pending: Vec::new().into_iter(),
+            current: None,
+            entries: Vec::new(),
+        }
+    }
+
+    fn is_selected(&self, entry: &ArchiveEntry) -> bool {
+        self.selected.is_empty() || self.selected.iter().any(|s| {
+            let s = s.trim_end_matches('/');
+            entry.path == s || entry.path.starts_with(&format!("{}/", s))
+        })
+    }
+
+    /// Valide la table des matières et crée les dossiers sélectionnés
+    fn start(&mut self, entries: Vec<ArchiveEntry>) -> Result<()> {
+        let mut offset = 0u64;
+        let mut files = Vec::new();
+        let symlinks = entries.iter()
+            .filter(|e| e.kind == EntryKind::Symlink)
+            .map(|e| safe_entry_path(&e.path))
+            .collect::<Result<HashSet<_>>>()?;
+        for entry in &entries {
+            let relative = safe_entry_path(&entry.path)?;
+            match entry.kind {
+                EntryKind::File => {
+                    if entry.offset != offset {
+                        return Err(CompressionError::InvalidFormat.into());
+                    }
+                    offset += entry.size;
+                    files.push(entry.clone());
+                }
+                EntryKind::Directory => {
+                    if self.is_selected(entry) {
+                        let path = self.root.join(&relative);
+                        ensure_no_symlink(self.root, &relative)?;
+                        fs::create_dir_all(path)?;
+                    }
+                }
+                EntryKind::Symlink => {
+                    let target = entry.symlink_target.as_deref().unwrap_or_default();
+                    if !is_safe_symlink(&relative, target, &symlinks) {
+                        return Err(CompressionError::IoError(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Unsafe symlink target for {}: {}", entry.path, target)
+                        )).into());
+                    }
+                }
+            }
+        }
+
+        self.toc.entries = Some(entries);
+        self.pending = files.into_iter();
+        self.open_next()
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<()> {
+        let mut data = data;
+        if self.toc.entries.is_none() {
+            match self.toc.feed(data)? {
+                Some(rest) => {
+                    data = rest;
+                    let entries = self.toc.entries.take().unwrap_or_default();
+                    self.start(entries)?;
+                }
+                None => return Ok(()),
+            }
+        }
+
+        while !data.is_empty() {
+            // Données au-delà du dernier fichier annoncé
+            let Some((_, writer, left)) = self.current.as_mut() else {
+                return Err(CompressionError::InvalidFormat.into());
+            };
+            let take = (*left).min(data.len() as u64) as usize;
+            if let Some(writer) = writer {
+                writer.write_all(&data[..take])?;
+            }
+            *left -= take as u64;
+            data = &data[take..];
+
+            if *left == 0 {
+                self.finish_file()?;
+                self.open_next()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ouvre le prochain fichier attendu ; les fichiers vides sont créés immédiatement
+    fn open_next(&mut self) -> Result<()> {
+        while self.current.is_none() {
+            let Some(entry) = self.pending.next() else {
+                return Ok(());
+            };
+            let writer = if self.is_selected(&entry) {
+                let relative = safe_entry_path(&entry.path)?;
+                ensure_no_symlink(self.root, &relative)?;
+                let path = self.root.join(&relative);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Some(AtomicFile::create(&path, self.overwrite)?)
+            } else {
+                None
+            };
+            let size = entry.size;
+            self.current = Some((entry, writer, size));
+            if size == 0 {
+                self.finish_file()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish_file(&mut self) -> Result<()> {
+        if let Some((entry, Some(mut writer), _)) = self.current.take() {
+            writer.file()?.set_modified(mtime(&entry))?;
+            let path = writer.commit()?;
+            set_mode(&path, entry.mode)?;
+            self.entries.push(entry);
+        }
+        Ok(())
+    }
+
+    /// Termine l'extraction : liens symboliques puis métadonnées des dossiers
+    fn finish(mut self) -> Result<Vec<ArchiveEntry>> {
+        // Flux tronqué : table absente ou fichiers incomplets
+        if self.toc.entries.is_none() || self.current.is_some() || self.pending.len() > 0 {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+
+        let entries = self.toc.entries.clone().unwrap_or_default();
+        let mut extracted = std::mem::take(&mut self.entries);
+
+        // Liens symboliques en dernier : aucun fichier n'est écrit à travers eux
+        for entry in entries.iter().filter(|e| e.kind == EntryKind::Symlink && self.is_selected(e)) {
+            let relative = safe_entry_path(&entry.path)?;
+            ensure_no_symlink(self.root, &relative)?;
+            create_symlink(entry.symlink_target.as_deref().unwrap_or_default(), &self.root.join(relative))?;
+            extracted.push(entry.clone());
+        }
+
+        // Dossiers en dernier : leur date serait modifiée par la création de leur contenu
+        for entry in entries.iter().rev().filter(|e| e.kind == EntryKind::Directory && self.is_selected(e)) {
+            let path = self.root.join(safe_entry_path(&entry.path)?);
+            // Meilleur effort : tous les systèmes ne permettent pas d'ouvrir un dossier
+            if let Ok(dir) = File::open(&path) {
+                let _ = dir.set_modified(mtime(entry));
+            }
+            set_mode(&path, entry.mode)?;
+            extracted.push(entry.clone());
+        }
+
+        Ok(extracted)
+    }
+}
+
+/// Convertit un chemin d'entrée en chemin relatif sûr (ni absolu, ni `..`)
+fn safe_entry_path(path: &str) -> Result<PathBuf> {
+    let mut relative = PathBuf::new();
+    for component in path.split('/') {
+        let unsafe_component = component.is_empty()
+            || component == "."
+            || component == ".."
+            || component.contains(['\\', ':', '\0']);
+        if unsafe_component {
+            return Err(CompressionError::IoError(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsafe entry path: {}", path)
+            )).into());
+        }
+        relative.push(component);
+    }
+
+    // Double vérification avec l'analyse de chemins de la plateforme
+    if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(CompressionError::IoError(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsafe entry path: {}", path)
+        )).into());
+    }
+    Ok(relative)
+}
+
+/// Un lien est accepté si sa cible est relative et reste dans le dossier d'extraction
+fn is_safe_symlink(link: &Path, target: &str, symlinks: &HashSet<PathBuf>) -> bool {
+    if target.is_empty() || target.starts_with('/') || target.contains(['\\', ':']) {
+        return false;
+    }
+    let mut path = link.parent().map(Path::to_path_buf).unwrap_or_default();
+    // Au-delà d'un autre lien de l'archive, `..` ne se résout plus lexicalement
+    let mut through_symlink = false;
+    for component in target.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if through_symlink || !path.pop() {
+                    return false;
+                }
+            }
+            _ => {
+                path.push(component);
+                through_symlink |= symlinks.contains(&path);
+            }
+        }
+    }
+    true
+}
+
+/// Refuse d'écrire à travers un lien symbolique déjà présent dans le dossier d'extraction
+fn ensure_no_symlink(root: &Path, relative: &Path) -> Result<()> {
+    let mut path = root.to_path_buf();
+    for component in relative.components() {
+        path.push(component);
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(CompressionError::IoError(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Refusing to extract through symlink: {}", path.display())
+                )).into());
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+fn mtime(entry: &ArchiveEntry) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(entry.mtime)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    // Pas de setuid/setgid/sticky depuis une archive
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_readonly(mode & 0o222 == 0);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, link: &Path) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &str, link: &Path) -> Result<()> {
+    if let Some(parent) = link.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    std::os::windows::fs::symlink_file(target, link)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionOptions;
+    use tempfile::tempdir;
+
+    fn sample_tree(root: &Path) -> Result<PathBuf> {
+        let project = root.join("project");
+        fs::create_dir_all(project.join("src/nested"))?;
+        fs::create_dir_all(project.join("empty"))?;
+        fs::write(project.join("README.md"), b"# Project\n".repeat(50))?;
+        fs::write(project.join("src/main.rs"), b"fn main() {}\n".repeat(500))?;
+        fs::write(project.join("src/nested/empty.txt"), b"")?;
+        fs::write(project.join("src/nested/data.bin"), (0..5000u32).map(|i| (i % 7) as u8).collect::<Vec<_>>())?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("src/main.rs", project.join("main-link"))?;
+        Ok(project)
+    }
+
+    #[test]
+    fn test_directory_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let project = sample_tree(dir.path())?;
+        let archive = dir.path().join("project.ntk");
+        let output = dir.path().join("out");
+
+        let compressor = Compressor::new(CompressionOptions {
+            block_size: 1024,
+            use_encryption: true,
+            password: Some("test123".into()),
+            key_derivation: crate::KeyDerivation::Pbkdf2 { iterations: 1_000 },
+            ..Default::default()
+        });
+        compressor.compress(&project, &archive)?;
+
+        let entries = compressor.list_entries(&archive)?;
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&"project/src/nested/data.bin"));
+        assert!(paths.contains(&"project/empty"));
+
+        compressor.decompress(&archive, &output)?;
+        for file in ["README.md", "src/main.rs", "src/nested/empty.txt", "src/nested/data.bin"] {
+            assert_eq!(fs::read(output.join("project").join(file))?, fs::read(project.join(file))?);
+        }
+        assert!(output.join("project/empty").is_dir());
+        #[cfg(unix)]
+        assert_eq!(fs::read_link(output.join("project/main-link"))?, PathBuf::from("src/main.rs"));
+
+        // Le dossier existant n'est ni complété ni remplacé hors de `Overwrite`
+        let renaming = Compressor::new(CompressionOptions {
+            overwrite: OverwritePolicy::Rename,
+            ..compressor.options.clone()
+        });
+        let renamed = renaming.decompress(&archive, &output)?;
+        assert_eq!(renamed, dir.path().join("out (1)"));
+        assert!(renamed.join("project/README.md").is_file());
+        let failing = Compressor::new(CompressionOptions {
+            overwrite: OverwritePolicy::Fail,
+            ..compressor.options.clone()
+        });
+        assert!(failing.decompress(&archive, &output).is_err());
+        let leftovers = fs::read_dir(dir.path())?
+            .filter(|e| e.as_ref().is_ok_and(|e| e.file_name().to_string_lossy().ends_with(".tmp")))
+            .count();
+        assert_eq!(leftovers, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_selected_entries() -> Result<()> {
+        let dir = tempdir()?;
+        let project = sample_tree(dir.path())?;
+        let archive = dir.path().join("project.ntk");
+        let output = dir.path().join("out");
+
+        let compressor = Compressor::new(CompressionOptions { block_size: 1024, ..Default::default() });
+        compressor.compress_entries(&[&project], &archive)?;
+
+        let extracted = compressor.extract_entries(&archive, &output, &["project/src/nested".to_string()])?;
+        assert_eq!(extracted.len(), 3);
+        assert!(output.join("project/src/nested/data.bin").is_file());
+        assert!(!output.join("project/README.md").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsafe_paths_are_rejected() {
+        for path in ["../evil", "a/../../evil", "/etc/passwd", "a//b", "C:/evil", "a\\..\\b", ""] {
+            assert!(safe_entry_path(path).is_err(), "{}", path);
+        }
+        assert!(safe_entry_path("project/src/main.rs").is_ok());
+
+        let none = HashSet::new();
+        assert!(is_safe_symlink(Path::new("a/link"), "../b", &none));
+        assert!(!is_safe_symlink(Path::new("a/link"), "../../b", &none));
+        assert!(!is_safe_symlink(Path::new("link"), "/etc/passwd", &none));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_chained_symlinks_do_not_escape() -> Result<()> {
+        let dir = tempdir()?;
+        let project = dir.path().join("project");
+        let output = dir.path().join("out");
+        let archive = dir.path().join("links.ntk");
+
+        // Chacun sûr isolément : `p/y` mène à `project`, `x` remonte d'un cran depuis `p/y`
+        fs::create_dir_all(project.join("p"))?;
+        std::os::unix::fs::symlink("..", project.join("p/y"))?;
+        std::os::unix::fs::symlink("p/y/..", project.join("x"))?;
+
+        let compressor = Compressor::new(CompressionOptions::default());
+        compressor.compress_entries(&[&project], &archive)?;
+        let err = compressor.extract_all(&archive, &output).unwrap_err();
+        assert!(err.to_string().contains("Unsafe symlink target for project/x"), "{}", err);
+        assert!(fs::symlink_metadata(output.join("project/x")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_traversal_entry_is_not_extracted() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("evil.ntk");
+        let output = dir.path().join("out");
+
+        // Archive forgée dont une entrée sort du dossier d'extraction
+        let entries = vec![ArchiveEntry {
+            path: "../evil.txt".into(),
+            kind: EntryKind::File,
+            size: 4,
+            mode: 0o644,
+            mtime: 0,
+            symlink_target: None,
+            offset: 0,
+        }];
+        let toc = serde_json::to_vec(&entries)?;
+        let mut stream = (toc.len() as u64).to_le_bytes().to_vec();
+        stream.extend_from_slice(&toc);
+        stream.extend_from_slice(b"evil");
+
+        let compressor = Compressor::new(CompressionOptions::default());
+        let mut header = compressor.new_header("evil", stream.len() as u64)?;
+        header.multi_entry = true;
+        let header_bytes = compressor.encode_header(&header, &[], &[])?;
+        let mut file = File::create(&archive)?;
+        file.write_all(&header_bytes)?;
+        let mut frames = FrameWriter::new(file, header_bytes.len() as u64, 0)?;
+        compressor.compress_stream(&mut &stream[..], &[stream.len() as u64], &header_bytes, &[], &[], &mut frames)?;
+        let footer = frames.end_blocks()?;
+        frames.finish(&serde_json::to_vec(&footer)?)?;
+
+        assert!(compressor.extract_all(&archive, &output).is_err());
+        assert!(!dir.path().join("evil.txt").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! Stéganographie audio : archive cachée dans les bits de poids faible des échantillons
+//! PCM d'un fichier WAV (16 ou 24 bits, mono ou stéréo)
+//!
+//! Même conteneur, même ordre dérivé du mot de passe et mêmes vérifications que pour
+//! les images (voir `stego`) ; chaque échantillon est un emplacement.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+use crate::output::AtomicFile;
+use crate::stego::{embed, extract, stego_error, Cover};
+use crate::volume::ArchiveReader;
+use crate::{Compressor, SteganographyError};
+
+/// Échantillons d'un fichier WAV PCM entier
+struct AudioCover {
+    spec: WavSpec,
+    samples: Vec<i32>,
+}
+
+impl AudioCover {
+    fn open(path: &Path) -> Result<Self> {
+        let reader = WavReader::open(path).map_err(audio_error)?;
+        let spec = reader.spec();
+        if spec.sample_format != SampleFormat::Int || !matches!(spec.bits_per_sample, 16 | 24) {
+            return Err(stego_error(SteganographyError::Cover(
+                "unsupported WAV format: only 16 and 24-bit PCM are supported".into()
+            )));
+        }
+        let samples = reader.into_samples::<i32>().collect::<Result<Vec<_>, _>>().map_err(audio_error)?;
+        Ok(Self { spec, samples })
+    }
+
+    /// Écrit le fichier WAV, avec le format d'origine
+    fn write(&self, path: &Path, compressor: &Compressor) -> Result<PathBuf> {
+        let mut output_file = AtomicFile::create(path, compressor.options.overwrite)?;
+        let mut writer = WavWriter::new(&mut output_file, self.spec).map_err(audio_error)?;
+        for &sample in &self.samples {
+            writer.write_sample(sample).map_err(audio_error)?;
+        }
+        writer.finalize().map_err(audio_error)?;
+        output_file.commit()
+    }
+}
+
+impl Cover for AudioCover {
+    fn slots(&self) -> u64 {
+        self.samples.len() as u64
+    }
+
+    fn low_byte(&self, slot: u64) -> u8 {
+        self.samples[slot as usize] as u8
+    }
+
+    // Les bits de poids fort (et le signe) ne changent pas : l'échantillon reste dans sa plage
+    fn set_low_byte(&mut self, slot: u64, value: u8) {
+        let sample = &mut self.samples[slot as usize];
+        *sample = (*sample & !0xff) | value as i32;
+    }
+}
+
+fn audio_error(e: hound::Error) -> anyhow::Error {
+    match e {
+        hound::Error::IoError(e) => e.into(),
+        e => stego_error(SteganographyError::Cover(e.to_string())),
+    }
+}
+
+impl Compressor {
+    /// Cache une archive dans un fichier WAV ; renvoie le chemin réellement écrit (voir
+    /// `OverwritePolicy::Rename`)
+    pub fn hide_in_audio<P: AsRef<Path>>(&self, archive_path: P, audio_path: P, output_path: P) -> Result<PathBuf> {
+        let mut archive_data = Vec::new();
+        ArchiveReader::open(archive_path.as_ref())?.read_to_end(&mut archive_data)?;
+        let mut cover = AudioCover::open(audio_path.as_ref())?;
+        embed(&mut cover, self.options.password.as_deref(), self.steganography_bits()?, &archive_data)?;
+        cover.write(output_path.as_ref(), self)
+    }
+
+    /// Renvoie le chemin réellement écrit (voir `OverwritePolicy::Rename`)
+    pub fn extract_from_audio<P: AsRef<Path>>(&self, audio_path: P, output_path: P) -> Result<PathBuf> {
+        let cover = AudioCover::open(audio_path.as_ref())?;
+        let archive_data = extract(&cover, self.options.password.as_deref())?;
+
+        let mut output_file = AtomicFile::create(output_path.as_ref(), self.options.overwrite)?;
+        output_file.write_all(&archive_data)?;
+        output_file.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionError, CompressionOptions};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hide_in_wav() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("rapport.ntk");
+        let extracted = dir.path().join("extrait.ntk");
+        let mut data = vec![0u8; 3000];
+        getrandom::getrandom(&mut data)?;
+        fs::write(&archive, &data)?;
+
+        let compressor = Compressor::new(CompressionOptions {
+            password: Some("terrain".into()),
+            ..Default::default()
+        });
+        for (channels, bits_per_sample) in [(1, 16), (2, 24)] {
+            let spec = WavSpec { channels, sample_rate: 8000, bits_per_sample, sample_format: SampleFormat::Int };
+            let cover = dir.path().join(format!("cover-{}.wav", bits_per_sample));
+            let stego = dir.path().join(format!("stego-{}.wav", bits_per_sample));
+            let amplitude = (1i32 << (bits_per_sample - 1)) - 1;
+            let mut writer = WavWriter::create(&cover, spec)?;
+            for i in 0..30_000i32 {
+                // Valeurs extrêmes comprises : la plage doit être respectée
+                writer.write_sample(if i % 1000 == 0 { -amplitude - 1 } else { (i * 37 % (2 * amplitude)) - amplitude })?;
+            }
+            writer.finalize()?;
+
+            compressor.hide_in_audio(&archive, &cover, &stego)?;
+            compressor.extract_from_audio(&stego, &extracted)?;
+            assert_eq!(fs::read(&extracted)?, data);
+            assert_eq!(WavReader::open(&stego)?.spec(), spec);
+        }
+
+        // Fichier ordinaire : aucune donnée cachée
+        let err = compressor.extract_from_audio(&dir.path().join("cover-16.wav"), &extracted).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CompressionError>(),
+            Some(CompressionError::SteganographyError(SteganographyError::NoHiddenData))
+        ));
+
+        // Capacité : 30 000 échantillons à 1 bit ne suffisent pas pour 5 000 octets
+        fs::write(&archive, vec![7u8; 5000])?;
+        assert!(compressor.hide_in_audio(&archive, &dir.path().join("cover-16.wav"), &extracted).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! Algorithmes de compression des blocs
+//!
+//! Depuis la version 7, chaque bloc commence (avant chiffrement) par l'identifiant du
+//! codec qui l'a produit : une archive peut donc mélanger plusieurs codecs. En mode
+//! `Auto`, le codec de chaque bloc est choisi en compressant un échantillon de son début.
+
+use std::io::{Read, Write};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::CompressionError;
+
+// Niveaux maximaux acceptés par chaque bibliothèque
+const LZ4_MAX_LEVEL: u32 = 16;
+const BROTLI_MAX_QUALITY: u32 = 11;
+const BROTLI_WINDOW: i32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+// Taille de l'échantillon compressé à l'essai en mode automatique
+const SAMPLE_SIZE: usize = 64 * 1024;
+// Niveaux rapides des essais : `level` ne sert qu'à l'encodage final du bloc
+const LZ4_PROBE_LEVEL: u32 = 0;
+const ZSTD_PROBE_LEVEL: u32 = 3;
+const BROTLI_PROBE_QUALITY: u32 = 5;
+// Au-delà de ce rapport (taille compressée / taille d'origine), le bloc est stocké tel quel
+const STORED_RATIO: f64 = 0.97;
+
+/// Codec utilisé pour compresser les blocs ; `level` est interprété par chacun
+/// (zstd 1-22, LZ4 0-16 avec 0 = mode rapide, Brotli 0-11) et borné au besoin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    /// Zstandard : bon compromis vitesse / taux
+    #[default]
+    Zstd,
+    /// LZ4 : compression et décompression les plus rapides
+    Lz4,
+    /// Brotli : meilleur taux, compression lente
+    Brotli,
+    /// Aucune compression (données déjà compressées)
+    Stored,
+    /// Choix par bloc selon `CompressionOptions::codec_policy`
+    Auto,
+}
+
+/// Compromis vitesse / taux du mode automatique : gain de taille minimal exigé
+/// pour préférer un codec plus lent (LZ4, puis zstd, puis Brotli)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecPolicy {
+    /// Gain d'au moins 20 %
+    Speed,
+    /// Gain d'au moins 5 %
+    #[default]
+    Balanced,
+    /// Le plus petit résultat l'emporte
+    Ratio,
+}
+
+impl CodecPolicy {
+    fn min_gain(self) -> f64 {
+        match self {
+            Self::Speed => 0.20,
+            Self::Balanced => 0.05,
+            Self::Ratio => 0.0,
+        }
+    }
+}
+
+impl Codec {
+    fn id(self) -> u8 {
+        match self {
+            Self::Zstd => 0,
+            Self::Lz4 => 1,
+            Self::Brotli => 2,
+            Self::Stored => 3,
+            Self::Auto => unreachable!("le mode automatique est résolu avant l'encodage"),
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(Self::Zstd),
+            1 => Ok(Self::Lz4),
+            2 => Ok(Self::Brotli),
+            3 => Ok(Self::Stored),
+            _ => Err(CompressionError::CompressionError(format!("Unknown codec id {}", id)).into()),
+        }
+    }
+
+    /// Compresse un bloc, précédé de l'identifiant du codec retenu
+    pub(crate) fn encode_tagged(
+        self,
+        data: &[u8],
+        level: u32,
+        policy: CodecPolicy,
+        dictionary: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let mut codec = match self {
+            Self::Auto => select_codec(data, policy, dictionary)?,
+            codec => codec,
+        };
+
+        let mut payload = codec.encode(data, level, dictionary)?;
+        // Un bloc que la compression agrandit est stocké tel quel
+        if self == Self::Auto && payload.len() >= data.len() {
+            codec = Self::Stored;
+            payload = data.to_vec();
+        }
+
+        let mut tagged = Vec::with_capacity(payload.len() + 1);
+        tagged.push(codec.id());
+        tagged.extend_from_slice(&payload);
+        Ok(tagged)
+    }
+
+    /// Le dictionnaire n'est utilisé que par zstd
+    fn encode(self, data: &[u8], level: u32, dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+        let mut compressed = Vec::with_capacity(data.len());
+        match self {
+            Self::Zstd => {
+                let mut encoder = zstd::Encoder::with_dictionary(&mut compressed, level as i32, dictionary.unwrap_or_default())?;
+                encoder.write_all(data)?;
+                encoder.finish()?;
+            }
+            Self::Lz4 => {
+                let mut encoder = lz4::EncoderBuilder::new()
+                    .level(level.min(LZ4_MAX_LEVEL))
+                    .build(&mut compressed)?;
+                encoder.write_all(data)?;
+                encoder.finish().1?;
+            }
+            Self::Brotli => {
+                let params = brotli::enc::BrotliEncoderParams {
+                    quality: level.min(BROTLI_MAX_QUALITY) as i32,
+                    lgwin: BROTLI_WINDOW,
+                    ..Default::default()
+                };
+                brotli::BrotliCompress(&mut &data[..], &mut compressed, &params)?;
+            }
+            Self::Stored => compressed.extend_from_slice(data),
+            Self::Auto => unreachable!("le mode automatique est résolu avant l'encodage"),
+        }
+        Ok(compressed)
+    }
+
+    pub(crate) fn decode(self, data: &[u8], capacity: usize, dictionary: Option<&[u8]>) -> Result<Vec<u8>> {
+        let mut decoded = Vec::with_capacity(capacity);
+        match self {
+            Self::Zstd => {
+                zstd::Decoder::with_dictionary(data, dictionary.unwrap_or_default())?.read_to_end(&mut decoded)?;
+            }
+            Self::Lz4 => {
+                lz4::Decoder::new(data)?.read_to_end(&mut decoded)?;
+            }
+            Self::Brotli => {
+                brotli::Decompressor::new(data, BROTLI_BUFFER_SIZE).read_to_end(&mut decoded)?;
+            }
+            Self::Stored => decoded.extend_from_slice(data),
+            Self::Auto => return Err(CompressionError::InvalidFormat.into()),
+        }
+        Ok(decoded)
+    }
+}
+
+/// Compresse à l'essai le début du bloc avec chaque codec, du plus rapide au plus lent,
+/// à des niveaux rapides quel que soit le niveau demandé
+fn select_codec(data: &[u8], policy: CodecPolicy, dictionary: Option<&[u8]>) -> Result<Codec> {
+    let sample = &data[..data.len().min(SAMPLE_SIZE)];
+    if sample.is_empty() {
+        return Ok(Codec::Stored);
+    }
+
+    // LZ4 sert aussi de test d'entropie : s'il ne gagne rien, les autres ne sont pas essayés
+    let mut best = Codec::Lz4;
+    let mut best_size = Codec::Lz4.encode(sample, LZ4_PROBE_LEVEL, None)?.len();
+    if best_size as f64 > sample.len() as f64 * STORED_RATIO {
+        return Ok(Codec::Stored);
+    }
+
+    for (codec, level) in [(Codec::Zstd, ZSTD_PROBE_LEVEL), (Codec::Brotli, BROTLI_PROBE_QUALITY)] {
+        let size = codec.encode(sample, level, dictionary)?.len();
+        if (size as f64) < best_size as f64 * (1.0 - policy.min_gain()) {
+            best = codec;
+            best_size = size;
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_selection() -> Result<()> {
+        // Données aléatoires : incompressibles, stockées telles quelles
+        let mut random = vec![0u8; 32 * 1024];
+        getrandom::getrandom(&mut random)?;
+        assert_eq!(select_codec(&random, CodecPolicy::Balanced, None)?, Codec::Stored);
+        let tagged = Codec::Auto.encode_tagged(&random, 3, CodecPolicy::Balanced, None)?;
+        assert_eq!(tagged[0], Codec::Stored.id());
+        assert_eq!(Codec::from_id(tagged[0])?.decode(&tagged[1..], 0, None)?, random);
+
+        // Texte répétitif : compressé, quelle que soit la politique
+        let text = b"The quick brown fox jumps over the lazy dog. ".repeat(1000);
+        for policy in [CodecPolicy::Speed, CodecPolicy::Balanced, CodecPolicy::Ratio] {
+            let tagged = Codec::Auto.encode_tagged(&text, 3, policy, None)?;
+            let codec = Codec::from_id(tagged[0])?;
+            assert_ne!(codec, Codec::Stored);
+            assert!(tagged.len() < text.len() / 10);
+            assert_eq!(codec.decode(&tagged[1..], text.len(), None)?, text);
+        }
+        Ok(())
+    }
+}
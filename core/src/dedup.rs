@@ -0,0 +1,311 @@
+//! Découpage selon le contenu et déduplication
+//!
+//! En mode `Chunking::ContentDefined`, les points de coupe sont choisis par FastCDC
+//! d'après le contenu : des données identiques, même décalées ou présentes dans
+//! plusieurs fichiers, donnent les mêmes morceaux. Chaque morceau distinct (empreinte
+//! BLAKE3) est stocké une seule fois ; l'index des blocs liste la suite des blocs qui
+//! forment les données d'origine.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+use std::time::Instant;
+
+use anyhow::Result;
+use fastcdc::v2020::{self as cdc, StreamCDC};
+use serde::{Deserialize, Serialize};
+
+use crate::incremental::BaseArchive;
+use crate::stream::BlockDecoder;
+use crate::{
+    block_aad, block_nonce, ArchiveFooter, ChunkRef, CompressionError, Compressor, FrameWriter, ParsedHeader, FOOTER_NONCE_INDEX,
+};
+
+/// Découpage des données en blocs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Chunking {
+    /// Blocs de `block_size` octets
+    #[default]
+    Fixed,
+    /// Morceaux de `block_size / 4` octets en moyenne (`block_size` au plus), coupés
+    /// selon le contenu ; les morceaux identiques ne sont stockés qu'une fois
+    ContentDefined,
+}
+
+/// Tailles minimale, moyenne et maximale des morceaux pour une taille de bloc
+fn chunk_sizes(block_size: usize) -> (u32, u32, u32) {
+    let max = (block_size as u32).clamp(cdc::MAXIMUM_MIN, cdc::MAXIMUM_MAX);
+    let avg = (max / 4).clamp(cdc::AVERAGE_MIN, cdc::AVERAGE_MAX);
+    let min = (avg / 4).clamp(cdc::MINIMUM_MIN, cdc::MINIMUM_MAX);
+    (min, avg, max)
+}
+
+/// Nouveau bloc : index, contenu et position dans les données d'origine
+type NewBlock = (u32, Vec<u8>, u64);
+
+impl Compressor {
+    /// Variante de `compress_stream` pour `Chunking::ContentDefined` et les archives
+    /// incrémentales ; selon le contenu, aucun morceau ne franchit la limite d'un segment
+    pub(crate) fn compress_chunked<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        segments: &[u64],
+        header_bytes: &[u8],
+        key: &[u8],
+        nonce_prefix: &[u8],
+        frames: &mut FrameWriter<W>,
+    ) -> Result<()> {
+        let (min_size, avg_size, max_size) = chunk_sizes(self.options.block_size);
+        let block_size = self.options.block_size.max(1) as u64;
+        let base = self.base_index()?;
+        let total_size: u64 = segments.iter().sum();
+        let window = self.in_flight_blocks();
+        let start = Instant::now();
+
+        rayon::in_place_scope(|scope| {
+            let (sender, receiver) = mpsc::channel::<(u32, Vec<u8>, u64, Result<Vec<u8>>)>();
+            let spawn = |(index, chunk, position): NewBlock, is_final: bool| {
+                let sender = sender.clone();
+                scope.spawn(move |_| {
+                    let nonce = block_nonce(nonce_prefix, index);
+                    let aad = block_aad(header_bytes, index, is_final);
+                    let result = self.compress_block(&chunk, key, &nonce, &aad);
+                    // Le récepteur a disparu si l'écriture a échoué : le résultat est abandonné
+                    let _ = sender.send((index, chunk, position, result));
+                });
+            };
+
+            // Empreinte du contenu -> bloc qui le stocke, ici ou dans la base
+            let mut known: HashMap<blake3::Hash, ChunkRef> = HashMap::new();
+            for (entry, &hash) in base.iter().flat_map(|base| base.hashes.iter().enumerate()) {
+                known.entry(hash).or_insert(ChunkRef::Base(entry));
+            }
+            let mut stored = 0usize;
+            // Dernier nouveau bloc : on ne sait pas encore s'il termine l'archive
+            let mut pending: Option<NewBlock> = None;
+            let mut ready = BTreeMap::new();
+            let mut spawned = 0u32;
+            let mut written = 0u32;
+            let mut read_bytes = 0u64;
+
+            // Traite un morceau dans l'ordre des données ; `None` une fois tout lu
+            let mut feed = |chunk: Option<Vec<u8>>| -> Result<()> {
+                let done = chunk.is_none();
+                if done && read_bytes != total_size {
+                    return Err(CompressionError::IoError(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Input changed during compression"
+                    )).into());
+                }
+                if let Some(chunk) = chunk {
+                    let position = read_bytes;
+                    read_bytes += chunk.len() as u64;
+                    let new_block = ChunkRef::Block(stored);
+                    let chunk_ref = *known.entry(blake3::hash(&chunk)).or_insert(new_block);
+                    frames.add_chunk(chunk_ref, &chunk);
+                    if chunk_ref == new_block {
+                        if stored >= FOOTER_NONCE_INDEX as usize - 1 {
+                            return Err(CompressionError::CompressionError("Too many blocks for this block size".into()).into());
+                        }
+                        let index = stored as u32;
+                        stored += 1;
+                        if let Some(previous) = pending.replace((index, chunk, position)) {
+                            spawn(previous, false);
+                            spawned += 1;
+                        }
+                    }
+                }
+                if done {
+                    // Au moins un bloc stocké (entrée vide, ou entièrement présente dans la base) :
+                    // un bloc vide en fin de suite
+                    if stored == 0 {
+                        frames.add_chunk(ChunkRef::Block(0), &[]);
+                        pending = Some((0, Vec::new(), read_bytes));
+                        stored = 1;
+                    }
+                    if let Some(last) = pending.take() {
+                        spawn(last, true);
+                        spawned += 1;
+                    }
+                }
+
+                // Fenêtre pleine (ou tout est lu) : écrire les blocs prêts dans l'ordre
+                while written < spawned && (spawned - written >= window || done) {
+                    while !ready.contains_key(&written) {
+                        let (index, chunk, position, result) = receiver.recv()
+                            .map_err(|_| CompressionError::CompressionError("Compression worker stopped".into()))?;
+                        ready.insert(index, (chunk, position, result));
+                    }
+                    let (chunk, position, result) = ready.remove(&written).unwrap();
+                    frames.write_unique_block(&result?, &chunk, position)?;
+                    written += 1;
+                    self.report_progress(position + chunk.len() as u64, total_size, start);
+                }
+                Ok(())
+            };
+
+            // À blocs fixes, le découpage est celui des archives ordinaires, qui ignore les
+            // limites des fichiers : les blocs d'une base ordinaire se retrouvent
+            let segments = match self.options.chunking {
+                Chunking::ContentDefined => segments.to_vec(),
+                Chunking::Fixed => vec![total_size],
+            };
+            for len in segments {
+                let mut segment = reader.by_ref().take(len);
+                let chunks: Box<dyn Iterator<Item = io::Result<Vec<u8>>>> = match self.options.chunking {
+                    Chunking::ContentDefined => Box::new(
+                        StreamCDC::new(segment, min_size, avg_size, max_size)
+                            .map(|chunk| chunk.map(|chunk| chunk.data).map_err(io::Error::from))
+                    ),
+                    Chunking::Fixed => Box::new(std::iter::from_fn(move || {
+                        let mut chunk = Vec::new();
+                        match segment.by_ref().take(block_size).read_to_end(&mut chunk) {
+                            Ok(0) => None,
+                            Ok(_) => Some(Ok(chunk)),
+                            Err(e) => Some(Err(e)),
+                        }
+                    })),
+                };
+                for chunk in chunks {
+                    self.check_cancelled()?;
+                    feed(Some(chunk?))?;
+                }
+            }
+            feed(None)
+        })
+    }
+}
+
+/// Suite des blocs d'une archive dédupliquée, pendant une lecture dans l'ordre : les
+/// blocs déjà transmis qu'elle référence à nouveau sont relus à l'aide de l'index, ceux
+/// de l'archive de base dans celle-ci
+pub(crate) struct ChunkSequence<'a> {
+    compressor: &'a Compressor,
+    parsed: &'a ParsedHeader,
+    key: &'a [u8],
+    footer: &'a ArchiveFooter,
+    base: Option<BaseArchive>,
+    sequence: Vec<ChunkRef>,
+    /// Position d'origine de la première occurrence de chaque bloc
+    starts: Vec<u64>,
+    next: usize,
+}
+
+impl<'a> ChunkSequence<'a> {
+    /// `None` si l'archive n'est pas dédupliquée
+    pub(crate) fn new(
+        compressor: &'a Compressor,
+        parsed: &'a ParsedHeader,
+        key: &'a [u8],
+        footer: &'a ArchiveFooter,
+    ) -> Result<Option<Self>> {
+        if footer.chunks.is_none() {
+            return Ok(None);
+        }
+        let base = compressor.open_base(parsed)?;
+        let starts = footer.block_starts(&base.as_ref().map(|base| base.sizes()).unwrap_or_default())?;
+        let sequence = footer.sequence();
+        Ok(Some(Self { compressor, parsed, key, footer, base, sequence, starts, next: 0 }))
+    }
+
+    /// Transmet les références qui précèdent le bloc `until` (toutes les restantes si
+    /// `None`) puis passe ce bloc, transmis par l'appelant
+    pub(crate) fn emit<R, F>(
+        &mut self,
+        input: &mut R,
+        decoder: &mut BlockDecoder,
+        until: Option<u32>,
+        on_block: &mut F,
+    ) -> Result<ControlFlow<()>>
+    where
+        R: Read + Seek,
+        F: FnMut(u64, &[u8]) -> Result<ControlFlow<()>>,
+    {
+        while let Some(&chunk) = self.sequence.get(self.next) {
+            self.next += 1;
+            if until.is_some_and(|until| chunk == ChunkRef::Block(until as usize)) {
+                return Ok(ControlFlow::Continue(()));
+            }
+
+            // La lecture dans l'ordre reprend là où elle s'était arrêtée
+            let resume = input.stream_position()?;
+            let (data, frame_offset) = match (chunk, &mut self.base) {
+                (ChunkRef::Block(index), _) => {
+                    let data = self.compressor.read_indexed_block(
+                        self.parsed, self.key, self.footer, input, index, self.starts[index]
+                    )?;
+                    input.seek(SeekFrom::Start(resume))?;
+                    (data, self.footer.blocks[index].offset)
+                }
+                (ChunkRef::Base(entry), Some(base)) => (base.read_entry(self.compressor, entry)?, resume),
+                (ChunkRef::Base(_), None) => return Err(CompressionError::InvalidFormat.into()),
+            };
+
+            decoder.accept_reference(&data);
+            if on_block(frame_offset - self.parsed.raw.len() as u64, &data)?.is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+        match until {
+            // Trame absente de la suite des blocs
+            Some(_) => Err(CompressionError::InvalidFormat.into()),
+            None => Ok(ControlFlow::Continue(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionOptions, KeyDerivation, NtkReader};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_content_defined_chunking_deduplicates() -> Result<()> {
+        let dir = tempdir()?;
+        let mut base = vec![0u8; 200 * 1024];
+        getrandom::getrandom(&mut base)?;
+        // Même contenu, décalé dans le second fichier
+        let shifted = [&base[..1000], &base[..]].concat();
+        let first = dir.path().join("first.bin");
+        let second = dir.path().join("second.bin");
+        fs::write(&first, &base)?;
+        fs::write(&second, &shifted)?;
+
+        let options = CompressionOptions {
+            block_size: 16 * 1024,
+            use_encryption: true,
+            password: Some("dedup".into()),
+            key_derivation: KeyDerivation::Pbkdf2 { iterations: 1000 },
+            ..Default::default()
+        };
+        let fixed = Compressor::new(options.clone());
+        let chunked = Compressor::new(CompressionOptions { chunking: Chunking::ContentDefined, ..options });
+
+        let fixed_archive = dir.path().join("fixed.ntk");
+        let chunked_archive = dir.path().join("chunked.ntk");
+        fixed.compress_entries(&[&first, &second], &fixed_archive)?;
+        chunked.compress_entries(&[&first, &second], &chunked_archive)?;
+        assert!(fs::metadata(&chunked_archive)?.len() < fs::metadata(&fixed_archive)?.len() * 2 / 3);
+
+        let output = dir.path().join("out");
+        chunked.decompress(&chunked_archive, &output)?;
+        assert_eq!(fs::read(output.join("first.bin"))?, base);
+        assert_eq!(fs::read(output.join("second.bin"))?, shifted);
+
+        // Fichier unique qui se répète : lecture partielle et flux
+        let repeated = dir.path().join("repeated.bin");
+        let repeated_archive = dir.path().join("repeated.ntk");
+        fs::write(&repeated, [&base[..], &base[..]].concat())?;
+        chunked.compress(&repeated, &repeated_archive)?;
+        assert!(fs::metadata(&repeated_archive)?.len() < base.len() as u64 * 3 / 2);
+        chunked.verify(&repeated_archive)?;
+        let range = chunked.read_range(&repeated_archive, base.len() as u64 + 5000, 10_000)?;
+        assert_eq!(range, &base[5000..15_000]);
+        assert!(NtkReader::new(chunked.clone(), fs::File::open(&repeated_archive)?).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Dictionnaires zstd : entraînement, chargement et enregistrement dans l'archive
+//!
+//! Le dictionnaire est soit intégré juste après l'en-tête (il fait alors partie des
+//! données associées de chaque bloc), soit externe : l'archive n'enregistre que son
+//! identifiant et son empreinte, et le même fichier doit être fourni à la lecture.
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{CompressionError, Compressor, ParsedHeader};
+
+// Taille maximale acceptée pour un dictionnaire (protection contre un en-tête forgé)
+pub(crate) const MAX_DICTIONARY_SIZE: usize = 16 * 1024 * 1024;
+// Nombre magique des dictionnaires produits par l'entraînement zstd
+const ZSTD_DICT_MAGIC: u32 = 0xEC30_A437;
+
+/// Dictionnaire utilisé par une archive, tel qu'enregistré dans l'en-tête
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct DictionaryInfo {
+    /// Identifiant zstd (0 pour un dictionnaire brut)
+    pub(crate) id: u32,
+    /// Empreinte BLAKE3 (hex)
+    pub(crate) hash: String,
+    pub(crate) size: u64,
+    /// Intégré après l'en-tête, ou à fournir via `dictionary_path`
+    pub(crate) embedded: bool,
+}
+
+impl DictionaryInfo {
+    pub(crate) fn new(dictionary: &[u8], embedded: bool) -> Self {
+        Self {
+            id: dictionary_id(dictionary),
+            hash: blake3::hash(dictionary).to_hex().to_string(),
+            size: dictionary.len() as u64,
+            embedded,
+        }
+    }
+
+    fn matches(&self, dictionary: &[u8]) -> bool {
+        self.size == dictionary.len() as u64 && self.hash == blake3::hash(dictionary).to_hex().as_str()
+    }
+}
+
+impl Compressor {
+    /// Entraîne un dictionnaire zstd d'au plus `dictionary_size` octets sur des fichiers d'exemple
+    pub fn train_dictionary<P: AsRef<Path>>(&self, samples: &[P]) -> Result<Vec<u8>> {
+        let max_size = self.options.dictionary_size.min(MAX_DICTIONARY_SIZE);
+        zstd::dict::from_files(samples.iter().map(|p| p.as_ref()), max_size)
+            .map_err(|e| CompressionError::CompressionError(format!("Dictionary training failed: {}", e)).into())
+    }
+
+    /// Utilise ce dictionnaire pour compresser, et pour lire les archives qui y font référence
+    pub fn set_dictionary(&mut self, dictionary: Vec<u8>) {
+        self.dictionary = Some(dictionary).into();
+    }
+
+    /// Dictionnaire fourni, chargé depuis `dictionary_path` à la première utilisation
+    pub(crate) fn dictionary(&self) -> Result<Option<&[u8]>> {
+        if let Some(dictionary) = self.dictionary.get() {
+            return Ok(dictionary.as_deref());
+        }
+
+        let loaded = match &self.options.dictionary_path {
+            Some(path) => {
+                let dictionary = std::fs::read(path)?;
+                if dictionary.len() > MAX_DICTIONARY_SIZE {
+                    return Err(CompressionError::CompressionError("Dictionary is too large".into()).into());
+                }
+                Some(dictionary)
+            }
+            None => None,
+        };
+        Ok(self.dictionary.get_or_init(|| loaded).as_deref())
+    }
+
+    /// Dictionnaire nécessaire à la lecture des blocs de l'archive
+    pub(crate) fn archive_dictionary<'a>(&'a self, parsed: &'a ParsedHeader) -> Result<Option<&'a [u8]>> {
+        let Some(info) = &parsed.header.dictionary else {
+            return Ok(None);
+        };
+        if info.embedded {
+            return Ok(parsed.dictionary.as_deref());
+        }
+
+        match self.dictionary()? {
+            Some(dictionary) if info.matches(dictionary) => Ok(Some(dictionary)),
+            Some(_) => Err(CompressionError::CompressionError(format!(
+                "Dictionary does not match the one used by the archive (id {})", info.id
+            )).into()),
+            None => Err(CompressionError::CompressionError(format!(
+                "Archive requires an external dictionary (id {})", info.id
+            )).into()),
+        }
+    }
+}
+
+/// Identifiant enregistré dans un dictionnaire zstd entraîné
+fn dictionary_id(dictionary: &[u8]) -> u32 {
+    let word = |i: usize| dictionary.get(i..i + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+    match (word(0), word(4)) {
+        (Some(ZSTD_DICT_MAGIC), Some(id)) => id,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionOptions;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dictionary_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let mut samples = Vec::new();
+        for i in 0..200 {
+            let path = dir.path().join(format!("sample{}.json", i));
+            fs::write(&path, format!(
+                r#"{{"id":{},"name":"user{}","email":"user{}@example.com","active":{},"roles":["reader","writer"]}}"#,
+                i, i, i, i % 2 == 0
+            ))?;
+            samples.push(path);
+        }
+
+        let trainer = Compressor::new(CompressionOptions { dictionary_size: 4096, ..Default::default() });
+        let dictionary = trainer.train_dictionary(&samples)?;
+        assert!(!dictionary.is_empty() && dictionary.len() <= 4096);
+        let dictionary_path = dir.path().join("json.dict");
+        fs::write(&dictionary_path, &dictionary)?;
+
+        let input_path = &samples[7];
+        for external_dictionary in [false, true] {
+            let compressed_path = dir.path().join("dict.ntk");
+            let output_path = dir.path().join("dict_out.json");
+            let compressor = Compressor::new(CompressionOptions {
+                dictionary_path: Some(dictionary_path.to_string_lossy().into_owned()),
+                external_dictionary,
+                ..Default::default()
+            });
+            compressor.compress(input_path, &compressed_path)?;
+
+            // Sans le dictionnaire, seule l'archive qui l'intègre reste lisible
+            let plain = Compressor::new(CompressionOptions::default());
+            let result = plain.decompress(&compressed_path, &output_path);
+            assert_eq!(result.is_ok(), !external_dictionary);
+
+            compressor.decompress(&compressed_path, &output_path)?;
+            assert_eq!(fs::read(&output_path)?, fs::read(input_path)?);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+//! Archives incrémentales
+//!
+//! Avec `base_archive`, un bloc dont le contenu figure déjà dans l'archive de base n'est
+//! pas stocké : la suite des blocs le désigne par son rang dans la suite de la base. La
+//! base peut elle-même être incrémentale, la lecture remonte alors la chaîne ;
+//! `consolidate` réécrit une chaîne en une archive autonome.
+
+use std::io::{self, BufReader, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::parity::ParityReader;
+use crate::volume::{volume_path, ArchiveReader};
+use crate::{
+    file_name, ArchiveFooter, ChunkRef, CompressionError, Compressor, FileMetadata, FrameWriter, ParsedHeader,
+    VERSION_CHECKSUMS,
+};
+
+// Longueur maximale d'une chaîne d'archives incrémentales
+const MAX_CHAIN_LENGTH: usize = 256;
+
+/// Archive de base, telle qu'enregistrée dans l'en-tête
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct BaseInfo {
+    /// Nom du fichier, cherché à côté de l'archive si `base_archive` n'est pas fourni
+    pub(crate) name: String,
+    /// Empreinte BLAKE3 (hex) du contenu de la base
+    pub(crate) checksum: String,
+}
+
+/// Suite des blocs d'une archive de base, utilisée à la compression
+#[derive(Debug, Clone)]
+pub(crate) struct BaseIndex {
+    pub(crate) info: BaseInfo,
+    /// Empreinte de chaque élément de la suite
+    pub(crate) hashes: Vec<blake3::Hash>,
+}
+
+/// Archive de base ouverte pour la lecture, avec sa propre base
+pub(crate) struct BaseArchive {
+    parsed: ParsedHeader,
+    key: Vec<u8>,
+    footer: ArchiveFooter,
+    input: ParityReader<BufReader<ArchiveReader>>,
+    base: Option<Box<BaseArchive>>,
+    sequence: Vec<ChunkRef>,
+    /// Taille et empreinte de chaque élément de la suite
+    entries: Vec<(u64, blake3::Hash)>,
+    /// Position d'origine de la première occurrence de chaque bloc stocké
+    starts: Vec<u64>,
+}
+
+impl BaseArchive {
+    /// Taille de chaque élément de la suite des blocs
+    pub(crate) fn sizes(&self) -> Vec<u64> {
+        self.entries.iter().map(|&(size, _)| size).collect()
+    }
+
+    /// Contenu de l'élément `entry` de la suite, lu dans cette archive ou dans sa base
+    pub(crate) fn read_entry(&mut self, compressor: &Compressor, entry: usize) -> Result<Vec<u8>> {
+        match self.sequence.get(entry) {
+            Some(&ChunkRef::Block(index)) => compressor.read_indexed_block(
+                &self.parsed, &self.key, &self.footer, &mut self.input, index, self.starts[index]
+            ),
+            Some(&ChunkRef::Base(entry)) => match &mut self.base {
+                Some(base) => base.read_entry(compressor, entry),
+                None => Err(CompressionError::InvalidFormat.into()),
+            },
+            None => Err(CompressionError::InvalidFormat.into()),
+        }
+    }
+}
+
+impl Compressor {
+    /// Réécrit une archive incrémentale, avec le contenu de sa chaîne de bases, en une
+    /// archive autonome (options de ce compresseur, `base_archive` excepté)
+    pub fn consolidate<P: AsRef<Path>, Q: AsRef<Path>>(&self, archive: P, output: Q) -> Result<FileMetadata> {
+        let start = Instant::now();
+        let (mut input, parsed) = self.open_archive(archive.as_ref())?;
+        if parsed.header.base.is_none() {
+            return Err(CompressionError::CompressionError("archive is not incremental".into()).into());
+        }
+
+        let mut standalone = self.clone();
+        standalone.options.base_archive = None;
+        standalone.base_index = Default::default();
+
+        let (key, salt, nonce_prefix) = if standalone.options.use_encryption {
+            standalone.prepare_encryption()?
+        } else {
+            (vec![], vec![], vec![])
+        };
+        let original_size = parsed.header.original_size;
+        let mut header = standalone.new_header(&parsed.header.original_name, original_size)?;
+        header.multi_entry = parsed.header.multi_entry;
+        let header_bytes = standalone.encode_header(&header, &salt, &nonce_prefix)?;
+
+        let mut output_file = self.create_output(output.as_ref())?;
+        output_file.write_all(&header_bytes)?;
+        let mut frames = FrameWriter::new(output_file, header_bytes.len() as u64, self.options.parity_percent)?;
+
+        // Le contenu décodé passe par un tube, compressé au fur et à mesure
+        let (mut reader, mut writer) = io::pipe()?;
+        std::thread::scope(|scope| -> Result<()> {
+            let decoding = scope.spawn(move || {
+                self.decode_blocks(&parsed, &mut input, |_, data| {
+                    writer.write_all(data)?;
+                    Ok(ControlFlow::Continue(()))
+                })
+            });
+            let written = standalone.compress_stream(
+                &mut reader, &[original_size], &header_bytes, &key, &nonce_prefix, &mut frames
+            );
+            // Débloque le décodage si la compression s'est arrêtée avant la fin
+            drop(reader);
+            let decoded = decoding.join()
+                .map_err(|_| CompressionError::CompressionError("Decompression worker stopped".into()))?;
+            match decoded {
+                // Tube fermé par la compression : son erreur est la cause
+                Err(e) if written.is_err() && e.downcast_ref::<io::Error>()
+                    .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe) => written,
+                decoded => decoded.and(written),
+            }
+        })?;
+
+        let footer = frames.end_blocks()?;
+        let compressed_size = frames.finish(&standalone.seal_footer(&footer, &header_bytes, &key, &nonce_prefix)?)?;
+        let output_path = frames.into_inner().commit()?;
+
+        Ok(FileMetadata {
+            original_name: header.original_name,
+            original_size,
+            compressed_size,
+            compression_ratio: original_size as f64 / compressed_size as f64,
+            encrypted: standalone.options.use_encryption,
+            creation_time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)?
+                .as_secs(),
+            checksum: footer.checksum.unwrap_or_default(),
+            estimated_time: start.elapsed().as_secs_f64(),
+            output_path: Some(output_path.to_string_lossy().into_owned()),
+        })
+    }
+
+    /// Index de l'archive de base (`options.base_archive`), chargé une fois
+    pub(crate) fn base_index(&self) -> Result<Option<&BaseIndex>> {
+        if let Some(index) = self.base_index.get() {
+            return Ok(index.as_ref());
+        }
+
+        let loaded = match &self.options.base_archive {
+            Some(path) => {
+                let base = self.open_base_at(Path::new(path), None, 0)?;
+                let checksum = base.footer.checksum.clone().ok_or(CompressionError::InvalidFormat)?;
+                Some(BaseIndex {
+                    info: BaseInfo { name: file_name(Path::new(path))?, checksum },
+                    hashes: base.entries.iter().map(|&(_, hash)| hash).collect(),
+                })
+            }
+            None => None,
+        };
+        Ok(self.base_index.get_or_init(|| loaded).as_ref())
+    }
+
+    /// Base d'une archive incrémentale, ouverte avec sa chaîne ; `None` si l'archive est
+    /// autonome. `options.base_archive`, s'il est fourni, remplace la base enregistrée.
+    pub(crate) fn open_base(&self, parsed: &ParsedHeader) -> Result<Option<BaseArchive>> {
+        let path = self.options.base_archive.as_ref().map(PathBuf::from);
+        self.open_base_of(parsed, path, 0)
+    }
+
+    fn open_base_of(&self, parsed: &ParsedHeader, path: Option<PathBuf>, depth: usize) -> Result<Option<BaseArchive>> {
+        let Some(info) = &parsed.header.base else {
+            return Ok(None);
+        };
+        if depth >= MAX_CHAIN_LENGTH {
+            return Err(CompressionError::CompressionError("base archive chain is too long".into()).into());
+        }
+        let path = match (path, &parsed.location) {
+            (Some(path), _) => path,
+            (None, Some(location)) => location.with_file_name(&info.name),
+            (None, None) => return Err(CompressionError::CompressionError(format!(
+                "Archive requires its base archive ({})", info.name
+            )).into()),
+        };
+        self.open_base_at(&path, Some(info), depth).map(Some)
+    }
+
+    /// Ouvre `path` comme archive de base ; `expected` : base enregistrée par l'archive qui en dépend
+    fn open_base_at(&self, path: &Path, expected: Option<&BaseInfo>, depth: usize) -> Result<BaseArchive> {
+        if !path.exists() && !volume_path(path, 1).exists() {
+            return Err(CompressionError::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Missing base archive {}", path.display())
+            )).into());
+        }
+        let (mut input, parsed) = self.open_archive(path)?;
+        if parsed.header.version < VERSION_CHECKSUMS {
+            return Err(CompressionError::CompressionError(
+                "base archive must be format version 9 or later".into()
+            ).into());
+        }
+        let key = self.archive_key(&parsed)?;
+        let footer = self.read_footer(&parsed, &key, &mut input)?;
+        if let Some(expected) = expected {
+            if footer.checksum.as_deref() != Some(expected.checksum.as_str()) {
+                return Err(CompressionError::IntegrityError(format!(
+                    "base archive {} does not match the one used by the archive", path.display()
+                )).into());
+            }
+        }
+
+        let base = self.open_base_of(&parsed, None, depth + 1)?.map(Box::new);
+        let sequence = footer.sequence();
+        let entries = sequence.iter().map(|&chunk| match chunk {
+            ChunkRef::Block(index) => footer.blocks[index].hash.as_deref()
+                .and_then(|hash| blake3::Hash::from_hex(hash).ok())
+                .map(|hash| (footer.blocks[index].size, hash)),
+            ChunkRef::Base(entry) => base.as_ref().and_then(|base| base.entries.get(entry)).copied(),
+        }).collect::<Option<Vec<_>>>().ok_or(CompressionError::InvalidFormat)?;
+        let starts = footer.block_starts(&base.as_ref().map(|base| base.sizes()).unwrap_or_default())?;
+
+        let input = ParityReader::new(input, footer.parity.clone(), false);
+        Ok(BaseArchive { parsed, key, footer, input, base, sequence, entries, starts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chunking, CompressionOptions, KeyDerivation};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_incremental_chain_and_consolidate() -> Result<()> {
+        let dir = tempdir()?;
+        let mut day1 = vec![0u8; 256 * 1024];
+        getrandom::getrandom(&mut day1)?;
+        let day2 = [&day1[..100_000], b"inserted", &day1[100_000..]].concat();
+        let day3 = [&day2[..], b"appended"].concat();
+
+        let options = CompressionOptions {
+            block_size: 16 * 1024,
+            chunking: Chunking::ContentDefined,
+            use_encryption: true,
+            password: Some("chain".into()),
+            key_derivation: KeyDerivation::Pbkdf2 { iterations: 1000 },
+            ..Default::default()
+        };
+        let archive = |day: usize| dir.path().join(format!("day{}.ntk", day));
+        for (day, data) in [(1, &day1), (2, &day2), (3, &day3)] {
+            let input = dir.path().join("snapshot.bin");
+            fs::write(&input, data)?;
+            let base_archive = (day > 1).then(|| archive(day - 1).to_string_lossy().into_owned());
+            Compressor::new(CompressionOptions { base_archive, ..options.clone() }).compress(&input, &archive(day))?;
+        }
+        assert!(fs::metadata(archive(3))?.len() < fs::metadata(archive(1))?.len() / 4);
+
+        // La chaîne est retrouvée à côté de l'archive
+        let reader = Compressor::new(options.clone());
+        let output = dir.path().join("day3.bin");
+        reader.decompress(&archive(3), &output)?;
+        assert_eq!(fs::read(&output)?, day3);
+        assert_eq!(reader.read_range(archive(3), 99_990, 30)?, &day3[99_990..100_020]);
+
+        // Une archive incrémentale qui se déclare en version 12 est refusée
+        let mut downgraded = fs::read(archive(2))?;
+        let at = downgraded.windows(12).position(|w| w == b"\"version\":14").unwrap();
+        downgraded[at + 10..at + 12].copy_from_slice(b"12");
+        fs::write(dir.path().join("downgraded.ntk"), &downgraded)?;
+        let err = reader.get_metadata(dir.path().join("downgraded.ntk")).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CompressionError>(), Some(CompressionError::InvalidFormat)));
+
+        let standalone = dir.path().join("standalone.ntk");
+        reader.consolidate(archive(3), &standalone)?;
+        fs::remove_file(archive(1))?;
+        assert!(reader.verify(archive(3)).is_err());
+        fs::remove_file(&output)?;
+        reader.decompress(&standalone, &output)?;
+        assert_eq!(fs::read(&output)?, day3);
+        Ok(())
+    }
+}
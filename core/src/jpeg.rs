@@ -0,0 +1,997 @@
+//! Stéganographie JPEG : archive cachée dans les coefficients DCT quantifiés
+//!
+//! Le fichier est décodé jusqu'aux coefficients (JPEG séquentiel de Huffman, 8 bits) puis
+//! réencodé avec ses propres tables : les pixels ne sont jamais recalculés, aucune perte
+//! n'est ajoutée et la sortie reste un JPEG.
+//!
+//! Insertion F5 : chaque coefficient AC non nul, visité dans l'ordre dérivé du mot de passe,
+//! porte le bit de poids faible de son amplitude. Pour changer ce bit, l'amplitude baisse
+//! de 1 (jamais +1 / -1 comme JSteg, qui égalise les paires de l'histogramme) ; un
+//! coefficient devenu nul est ignoré à la lecture et le bit est réinséré plus loin
+//! (rétrécissement). Avec l'insertion matricielle (1, 2^k - 1, k), un groupe de 2^k - 1
+//! coefficients porte k bits au prix d'une modification au plus. k est choisi avant toute
+//! modification, le plus grand pour lequel l'archive tient malgré le rétrécissement. Même
+//! conteneur que pour les images (voir `stego`) ; son en-tête, inséré avec k = 1, donne k.
+//!
+//! Les plages de zéros allongées peuvent demander des symboles absents des tables de
+//! Huffman du fichier (tables optimisées) : elles sont alors recalculées.
+//!
+//! La résistance à un nouvel encodage n'est pas un objectif : une messagerie qui réduit la
+//! qualité recalcule les coefficients et détruit les données ; l'extraction le signale
+//! (`NoHiddenData` ou `CorruptedData`).
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::output::AtomicFile;
+use crate::stego::{
+    container_header, parse_container_header, stego_error, verify_container, SlotOrder, STEGO_HEADER_SIZE,
+};
+use crate::volume::ArchiveReader;
+use crate::{Compressor, SteganographyError};
+
+const SOI: u8 = 0xd8;
+const EOI: u8 = 0xd9;
+const SOS: u8 = 0xda;
+const DHT: u8 = 0xc4;
+const DRI: u8 = 0xdd;
+const BLOCK_SIZE: usize = 64;
+// Catégories maximales des coefficients d'un JPEG 8 bits
+const MAX_DC_CATEGORY: u8 = 11;
+const MAX_AC_CATEGORY: u8 = 10;
+// Blocs alloués au plus (128 octets chacun, soit 512 Mo ; ~180 Mpx en 4:2:0)
+const MAX_BLOCKS: usize = 4 << 20;
+// Insertion matricielle (1, 2^k - 1, k) : k au plus
+const MAX_MATRIX_K: u8 = 7;
+// En-tête du conteneur, toujours inséré avec k = 1
+const HEADER_BITS: u64 = STEGO_HEADER_SIZE as u64 * 8;
+
+/// Table de Huffman, dans les deux sens
+#[derive(Clone)]
+struct HuffmanTable {
+    /// Nombre de codes de chaque longueur (1 à 16)
+    counts: [u8; 16],
+    /// Code et longueur de chaque symbole (longueur 0 : absent)
+    codes: Vec<(u16, u8)>,
+    /// Plus grand code de chaque longueur (-1 : aucun)
+    max_code: [i32; 17],
+    /// Indice dans `values` moins le premier code de chaque longueur
+    offset: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl HuffmanTable {
+    fn new(counts: &[u8], values: Vec<u8>) -> Result<Self> {
+        let mut codes = vec![(0u16, 0u8); 256];
+        let mut max_code = [-1i32; 17];
+        let mut offset = [0i32; 17];
+        let mut code = 0u32;
+        let mut k = 0usize;
+        for len in 1..=16 {
+            let count = counts[len - 1] as usize;
+            offset[len] = k as i32 - code as i32;
+            for _ in 0..count {
+                codes[values[k] as usize] = (code as u16, len as u8);
+                code += 1;
+                k += 1;
+            }
+            if count > 0 {
+                max_code[len] = code as i32 - 1;
+            }
+            if code > 1 << len {
+                return Err(jpeg_error("invalid Huffman table"));
+            }
+            code <<= 1;
+        }
+        Ok(Self { counts: counts.try_into().unwrap(), codes, max_code, offset, values })
+    }
+
+    /// Table optimale pour ces fréquences (JPEG, annexe K.2 : codes de 16 bits au plus,
+    /// aucun code fait uniquement de 1)
+    fn optimal(frequencies: &[u64; 256]) -> Result<Self> {
+        // Symbole 256 réservé : il prend le code le plus long, retiré à la fin
+        let mut frequencies = frequencies.to_vec();
+        frequencies.push(1);
+        let mut code_size = [0usize; 257];
+        let mut others = [None; 257];
+        let smallest = |frequencies: &[u64], skip: Option<usize>| (0..257)
+            .filter(|&i| frequencies[i] > 0 && Some(i) != skip)
+            .min_by_key(|&i| (frequencies[i], std::cmp::Reverse(i)));
+        while let Some(mut c1) = smallest(&frequencies, None) {
+            let Some(mut c2) = smallest(&frequencies, Some(c1)) else {
+                break;
+            };
+            frequencies[c1] += frequencies[c2];
+            frequencies[c2] = 0;
+            code_size[c1] += 1;
+            while let Some(next) = others[c1] {
+                c1 = next;
+                code_size[c1] += 1;
+            }
+            others[c1] = Some(c2);
+            code_size[c2] += 1;
+            while let Some(next) = others[c2] {
+                c2 = next;
+                code_size[c2] += 1;
+            }
+        }
+
+        let mut bits = vec![0usize; 258];
+        for &size in code_size.iter().filter(|&&size| size > 0) {
+            bits[size] += 1;
+        }
+        // Codes trop longs : deux feuilles remontent, une feuille plus courte descend
+        for i in (17..bits.len()).rev() {
+            while bits[i] > 0 {
+                let mut j = i - 2;
+                while bits[j] == 0 {
+                    j -= 1;
+                }
+                bits[i] -= 2;
+                bits[i - 1] += 1;
+                bits[j + 1] += 2;
+                bits[j] -= 1;
+            }
+        }
+        let longest = (1..=16).rev().find(|&i| bits[i] > 0).unwrap_or(1);
+        bits[longest] -= 1;
+
+        let counts: Vec<u8> = bits[1..=16].iter().map(|&count| count as u8).collect();
+        let values = (1..code_size.len())
+            .flat_map(|size| (0..256).filter(move |&symbol| code_size[symbol] == size))
+            .map(|symbol| symbol as u8)
+            .collect();
+        Self::new(&counts, values)
+    }
+
+    /// Définition de la table `index` (classe et identifiant) dans un segment DHT
+    fn write_definition(&self, index: usize, segment: &mut Vec<u8>) {
+        segment.push((((index / 4) << 4) | (index % 4)) as u8);
+        segment.extend_from_slice(&self.counts);
+        segment.extend_from_slice(&self.values);
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u8> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = (code << 1) | reader.bit() as i32;
+            if code <= self.max_code[len] {
+                return Ok(self.values[(code + self.offset[len]) as usize]);
+            }
+        }
+        Err(jpeg_error("invalid Huffman code"))
+    }
+
+    fn encode(&self, writer: &mut BitWriter, symbol: u8) -> Result<()> {
+        match self.codes[symbol as usize] {
+            (_, 0) => Err(jpeg_error("symbol missing from Huffman table")),
+            (code, len) => {
+                writer.put(code as u32, len);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Lecture des données entropiques (octets de bourrage retirés)
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    available: u8,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0, buffer: 0, available: 0 }
+    }
+
+    // Au-delà des données ou sur un marqueur, des zéros (comme libjpeg)
+    fn bit(&mut self) -> u8 {
+        if self.available == 0 {
+            let mut byte = 0;
+            match self.data.get(self.position..self.position + 2) {
+                Some([0xff, 0x00]) => {
+                    byte = 0xff;
+                    self.position += 2;
+                }
+                Some([0xff, _]) => {}
+                _ => if let Some(&b) = self.data.get(self.position) {
+                    byte = b;
+                    self.position += 1;
+                },
+            }
+            self.buffer = byte as u32;
+            self.available = 8;
+        }
+        self.available -= 1;
+        ((self.buffer >> self.available) & 1) as u8
+    }
+
+    fn bits(&mut self, count: u8) -> i32 {
+        (0..count).fold(0, |value, _| (value << 1) | self.bit() as i32)
+    }
+
+    /// Marqueur RSTn : les bits restants de l'octet en cours sont du remplissage
+    fn restart(&mut self) {
+        self.available = 0;
+        if let Some([0xff, 0xd0..=0xd7]) = self.data.get(self.position..self.position + 2) {
+            self.position += 2;
+        }
+    }
+}
+
+/// Écriture des données entropiques, avec octets de bourrage
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    buffer: u32,
+    pending: u8,
+}
+
+impl BitWriter {
+    fn put(&mut self, bits: u32, count: u8) {
+        self.buffer = (self.buffer << count) | (bits & ((1 << count) - 1));
+        self.pending += count;
+        while self.pending >= 8 {
+            self.pending -= 8;
+            let byte = (self.buffer >> self.pending) as u8;
+            self.data.push(byte);
+            if byte == 0xff {
+                self.data.push(0);
+            }
+        }
+        self.buffer &= (1 << self.pending) - 1;
+    }
+
+    /// Complète l'octet en cours avec des 1
+    fn align(&mut self) {
+        if self.pending > 0 {
+            self.put(0x7f, 8 - self.pending);
+        }
+    }
+
+    fn restart(&mut self, number: usize) {
+        self.align();
+        self.data.extend_from_slice(&[0xff, 0xd0 + (number % 8) as u8]);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    /// Blocs par ligne (MCU complètes)
+    stride: usize,
+}
+
+/// Balayage : segment SOS, composantes (indice, table DC, table AC), tables et intervalle
+/// de reprise en vigueur à son début
+struct Scan {
+    header: Vec<u8>,
+    components: Vec<(usize, usize, usize)>,
+    tables: Vec<Option<HuffmanTable>>,
+    restart_interval: usize,
+}
+
+/// Segments recopiés tels quels, ou données entropiques réencodées
+enum Part {
+    Raw(Vec<u8>),
+    Scan(Scan),
+}
+
+/// Symbole de Huffman (table, symbole, bits qui le suivent et leur nombre) ou reprise
+enum Token {
+    Restart,
+    Symbol(usize, u8, u32, u8),
+}
+
+/// JPEG décodé jusqu'aux coefficients quantifiés (ordre zigzag)
+struct JpegCover {
+    parts: Vec<Part>,
+    width: usize,
+    height: usize,
+    components: Vec<Component>,
+    blocks: Vec<Vec<[i16; BLOCK_SIZE]>>,
+}
+
+impl JpegCover {
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.get(..2) != Some(&[0xff, SOI]) {
+            return Err(jpeg_error("not a JPEG file"));
+        }
+        let mut cover = Self {
+            parts: Vec::new(),
+            width: 0,
+            height: 0,
+            components: Vec::new(),
+            blocks: Vec::new(),
+        };
+        // Tables DC 0-3 puis AC 0-3
+        let mut tables: Vec<Option<HuffmanTable>> = vec![None; 8];
+        let mut restart_interval = 0;
+        let mut raw_start = 0;
+        let mut position = 2;
+
+        loop {
+            // Octets 0xff de remplissage avant le marqueur
+            while data.get(position) == Some(&0xff) && data.get(position + 1) == Some(&0xff) {
+                position += 1;
+            }
+            let marker = match data.get(position..position + 2) {
+                Some(&[0xff, marker]) => marker,
+                _ => return Err(jpeg_error("truncated or invalid JPEG file")),
+            };
+            position += 2;
+            match marker {
+                EOI => {
+                    cover.parts.push(Part::Raw(data[raw_start..].to_vec()));
+                    break;
+                }
+                0xd0..=0xd7 | 0x01 => continue,
+                _ => {}
+            }
+
+            let length = match data.get(position..position + 2) {
+                Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+                None => return Err(jpeg_error("truncated JPEG file")),
+            };
+            let Some(segment) = data.get(position + 2..position + length).filter(|_| length >= 2) else {
+                return Err(jpeg_error("truncated JPEG file"));
+            };
+            match marker {
+                0xc0 | 0xc1 => cover.read_frame(segment, data.len())?,
+                0xc2 | 0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => {
+                    return Err(jpeg_error("only baseline (sequential Huffman) JPEG files are supported"));
+                }
+                DHT => read_tables(segment, &mut tables)?,
+                DRI if segment.len() >= 2 => restart_interval = u16::from_be_bytes([segment[0], segment[1]]) as usize,
+                SOS => {
+                    let header_start = position - 2;
+                    position += length;
+                    let end = entropy_end(data, position);
+                    cover.parts.push(Part::Raw(data[raw_start..header_start].to_vec()));
+                    let mut scan = cover.read_scan_header(segment, &tables, restart_interval)?;
+                    scan.header = data[header_start..position].to_vec();
+                    cover.decode_scan(&scan, &data[position..end])?;
+                    cover.parts.push(Part::Scan(scan));
+                    raw_start = end;
+                    position = end;
+                    continue;
+                }
+                _ => {}
+            }
+            position += length;
+        }
+
+        if cover.components.is_empty() {
+            return Err(jpeg_error("JPEG file has no image data"));
+        }
+        Ok(cover)
+    }
+
+    fn read_frame(&mut self, segment: &[u8], file_size: usize) -> Result<()> {
+        if !self.components.is_empty() || segment.len() < 6 || segment[0] != 8 {
+            return Err(jpeg_error("only 8-bit JPEG files with a single frame are supported"));
+        }
+        self.height = u16::from_be_bytes([segment[1], segment[2]]) as usize;
+        self.width = u16::from_be_bytes([segment[3], segment[4]]) as usize;
+        let count = segment[5] as usize;
+        if self.height == 0 || self.width == 0 || count == 0 || segment.len() < 6 + 3 * count {
+            return Err(jpeg_error("invalid JPEG frame header"));
+        }
+        for c in 0..count {
+            let (id, sampling) = (segment[6 + 3 * c], segment[7 + 3 * c]);
+            let (h, v) = ((sampling >> 4) as usize, (sampling & 15) as usize);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) {
+                return Err(jpeg_error("invalid JPEG sampling factors"));
+            }
+            self.components.push(Component { id, h, v, stride: 0 });
+        }
+        // Taille annoncée bornée avant l'allocation : un bloc occupe au moins 2 bits du flux
+        let (columns, rows) = self.mcu_grid();
+        let total = self.components.iter()
+            .try_fold(0usize, |total, c| total.checked_add(columns.checked_mul(c.h * rows * c.v)?));
+        if !total.is_some_and(|total| total <= MAX_BLOCKS && total <= file_size.saturating_mul(4)) {
+            return Err(jpeg_error("JPEG frame is too large"));
+        }
+        for component in &mut self.components {
+            component.stride = columns * component.h;
+            self.blocks.push(vec![[0; BLOCK_SIZE]; columns * component.h * rows * component.v]);
+        }
+        Ok(())
+    }
+
+    /// MCU par ligne et par colonne d'un balayage entrelacé
+    fn mcu_grid(&self) -> (usize, usize) {
+        let h_max = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+        (self.width.div_ceil(8 * h_max), self.height.div_ceil(8 * v_max))
+    }
+
+    fn read_scan_header(&self, segment: &[u8], tables: &[Option<HuffmanTable>], restart_interval: usize) -> Result<Scan> {
+        let count = *segment.first().unwrap_or(&0) as usize;
+        if self.components.is_empty() || count == 0 || segment.len() < 4 + 2 * count {
+            return Err(jpeg_error("invalid JPEG scan header"));
+        }
+        let mut components = Vec::with_capacity(count);
+        for c in 0..count {
+            let (id, selectors) = (segment[1 + 2 * c], segment[2 + 2 * c]);
+            let Some(index) = self.components.iter().position(|component| component.id == id) else {
+                return Err(jpeg_error("JPEG scan refers to an unknown component"));
+            };
+            let (dc, ac) = ((selectors >> 4) as usize, 4 + (selectors & 15) as usize);
+            if dc >= 4 || ac >= 8 || tables[dc].is_none() || tables[ac].is_none() {
+                return Err(jpeg_error("JPEG scan refers to a missing Huffman table"));
+            }
+            components.push((index, dc, ac));
+        }
+        // Séquentiel : tous les coefficients, sans approximations successives
+        if segment[1 + 2 * count..4 + 2 * count] != [0, 63, 0] {
+            return Err(jpeg_error("only baseline (sequential Huffman) JPEG files are supported"));
+        }
+        Ok(Scan { header: Vec::new(), components, tables: tables.to_vec(), restart_interval })
+    }
+
+    /// Blocs d'un balayage dans l'ordre du flux : position dans le balayage, indice du
+    /// bloc, reprise (marqueur RSTn) avant le bloc
+    fn scan_order(&self, scan: &Scan) -> Vec<(usize, usize, bool)> {
+        let mut order = Vec::new();
+        let restart = |mcu: usize| scan.restart_interval > 0 && mcu > 0 && mcu.is_multiple_of(scan.restart_interval);
+        if let [(index, _, _)] = scan.components[..] {
+            // Non entrelacé : les blocs de la composante seule, sans compléter les MCU
+            let component = self.components[index];
+            let h_max = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+            let v_max = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+            let columns = (self.width * component.h).div_ceil(h_max).div_ceil(8);
+            let rows = (self.height * component.v).div_ceil(v_max).div_ceil(8);
+            for row in 0..rows {
+                for column in 0..columns {
+                    order.push((0, row * component.stride + column, restart(row * columns + column)));
+                }
+            }
+            return order;
+        }
+        let (columns, rows) = self.mcu_grid();
+        for mcu in 0..columns * rows {
+            let (row, column) = (mcu / columns, mcu % columns);
+            let mut first = true;
+            for (position, &(index, _, _)) in scan.components.iter().enumerate() {
+                let component = self.components[index];
+                for v in 0..component.v {
+                    for h in 0..component.h {
+                        let block = (row * component.v + v) * component.stride + column * component.h + h;
+                        order.push((position, block, first && restart(mcu)));
+                        first = false;
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    fn decode_scan(&mut self, scan: &Scan, data: &[u8]) -> Result<()> {
+        let mut reader = BitReader::new(data);
+        let mut predictors = vec![0i32; scan.components.len()];
+        for (position, block, restart) in self.scan_order(scan) {
+            if restart {
+                reader.restart();
+                predictors.fill(0);
+            }
+            let (index, dc, ac) = scan.components[position];
+            let (dc, ac) = (scan.tables[dc].as_ref().unwrap(), scan.tables[ac].as_ref().unwrap());
+            let coefficients = &mut self.blocks[index][block];
+
+            let size = dc.decode(&mut reader)?;
+            if size > MAX_DC_CATEGORY {
+                return Err(jpeg_error("corrupted JPEG data"));
+            }
+            let dc_value = predictors[position].checked_add(extend(reader.bits(size), size))
+                .and_then(|value| i16::try_from(value).ok())
+                .ok_or_else(|| jpeg_error("corrupted JPEG data"))?;
+            predictors[position] = dc_value as i32;
+            coefficients[0] = dc_value;
+            let mut k = 1;
+            while k < BLOCK_SIZE {
+                let symbol = ac.decode(&mut reader)?;
+                let (run, size) = ((symbol >> 4) as usize, symbol & 15);
+                if size == 0 {
+                    if run != 15 {
+                        break;
+                    }
+                    k += 16;
+                    continue;
+                }
+                k += run;
+                if k >= BLOCK_SIZE || size > MAX_AC_CATEGORY {
+                    return Err(jpeg_error("corrupted JPEG data"));
+                }
+                coefficients[k] = extend(reader.bits(size), size) as i16;
+                k += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Symboles d'un balayage dans l'ordre du flux
+    fn scan_tokens(&self, scan: &Scan, mut emit: impl FnMut(Token) -> Result<()>) -> Result<()> {
+        let mut predictors = vec![0i32; scan.components.len()];
+        for (position, block, restart) in self.scan_order(scan) {
+            if restart {
+                emit(Token::Restart)?;
+                predictors.fill(0);
+            }
+            let (index, dc, ac) = scan.components[position];
+            let coefficients = &self.blocks[index][block];
+
+            let (size, bits) = category(coefficients[0] as i32 - predictors[position]);
+            predictors[position] = coefficients[0] as i32;
+            emit(Token::Symbol(dc, size, bits, size))?;
+            let mut run = 0;
+            for &coefficient in &coefficients[1..] {
+                if coefficient == 0 {
+                    run += 1;
+                    continue;
+                }
+                while run > 15 {
+                    emit(Token::Symbol(ac, 0xf0, 0, 0))?;
+                    run -= 16;
+                }
+                let (size, bits) = category(coefficient as i32);
+                emit(Token::Symbol(ac, (run << 4) | size, bits, size))?;
+                run = 0;
+            }
+            if run > 0 {
+                emit(Token::Symbol(ac, 0x00, 0, 0))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Tables du balayage : celles du fichier, sauf si elles ne codent pas tous ses
+    /// symboles ; les tables recalculées (ou rétablies après un remplacement, suivi dans
+    /// `replaced`) sont définies dans un segment DHT ajouté à `data`
+    fn scan_tables(&self, scan: &Scan, replaced: &mut [bool; 8], data: &mut Vec<u8>) -> Result<Vec<Option<HuffmanTable>>> {
+        let mut frequencies = vec![[0u64; 256]; 8];
+        self.scan_tokens(scan, |token| {
+            if let Token::Symbol(table, symbol, _, _) = token {
+                frequencies[table][symbol as usize] += 1;
+            }
+            Ok(())
+        })?;
+
+        let mut tables = scan.tables.clone();
+        let mut segment = Vec::new();
+        for (index, frequencies) in frequencies.iter().enumerate() {
+            let Some(table) = &tables[index] else {
+                continue;
+            };
+            if !scan.components.iter().any(|&(_, dc, ac)| dc == index || ac == index) {
+                continue;
+            }
+            let complete = frequencies.iter().zip(&table.codes).all(|(&count, &(_, len))| count == 0 || len > 0);
+            if complete && !replaced[index] {
+                continue;
+            }
+            if !complete {
+                tables[index] = Some(HuffmanTable::optimal(frequencies)?);
+            }
+            replaced[index] = !complete;
+            tables[index].as_ref().unwrap().write_definition(index, &mut segment);
+        }
+        if !segment.is_empty() {
+            data.extend_from_slice(&[0xff, DHT]);
+            data.extend_from_slice(&(segment.len() as u16 + 2).to_be_bytes());
+            data.extend_from_slice(&segment);
+        }
+        Ok(tables)
+    }
+
+    fn encode_scan(&self, scan: &Scan, tables: &[Option<HuffmanTable>]) -> Result<Vec<u8>> {
+        let mut writer = BitWriter::default();
+        let mut restarts = 0;
+        self.scan_tokens(scan, |token| {
+            match token {
+                Token::Restart => {
+                    writer.restart(restarts);
+                    restarts += 1;
+                }
+                Token::Symbol(table, symbol, bits, size) => {
+                    tables[table].as_ref().unwrap().encode(&mut writer, symbol)?;
+                    writer.put(bits, size);
+                }
+            }
+            Ok(())
+        })?;
+        writer.align();
+        Ok(writer.data)
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut replaced = [false; 8];
+        for part in &self.parts {
+            match part {
+                Part::Raw(bytes) => data.extend_from_slice(bytes),
+                Part::Scan(scan) => {
+                    let tables = self.scan_tables(scan, &mut replaced, &mut data)?;
+                    data.extend_from_slice(&scan.header);
+                    data.extend_from_slice(&self.encode_scan(scan, &tables)?);
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    /// Coefficients AC, 63 par bloc, composante après composante
+    fn ac_count(&self) -> u64 {
+        self.blocks.iter().map(|blocks| blocks.len() as u64 * (BLOCK_SIZE as u64 - 1)).sum()
+    }
+
+    fn ac_mut(&mut self, position: u64) -> &mut i16 {
+        let mut position = position as usize;
+        for blocks in &mut self.blocks {
+            let count = blocks.len() * (BLOCK_SIZE - 1);
+            if position < count {
+                return &mut blocks[position / (BLOCK_SIZE - 1)][1 + position % (BLOCK_SIZE - 1)];
+            }
+            position -= count;
+        }
+        panic!("AC coefficient out of range");
+    }
+
+    fn ac(&self, position: u64) -> i16 {
+        let mut position = position as usize;
+        for blocks in &self.blocks {
+            let count = blocks.len() * (BLOCK_SIZE - 1);
+            if position < count {
+                return blocks[position / (BLOCK_SIZE - 1)][1 + position % (BLOCK_SIZE - 1)];
+            }
+            position -= count;
+        }
+        panic!("AC coefficient out of range");
+    }
+
+    /// Coefficients AC dont l'amplitude est au moins `min`
+    fn ac_at_least(&self, min: u16) -> u64 {
+        self.blocks.iter().flatten()
+            .map(|block| block[1..].iter().filter(|c| c.unsigned_abs() >= min).count() as u64)
+            .sum()
+    }
+
+    /// Octets insérés à coup sûr : avec k = 1, un coefficient d'amplitude 2 ou plus porte
+    /// toujours un bit (il ne peut pas devenir nul)
+    fn capacity(&self) -> u64 {
+        (self.ac_at_least(2).saturating_sub(HEADER_BITS) / 8).min(u32::MAX as u64)
+    }
+
+    /// Cache le conteneur : en-tête avec k = 1, puis les données avec le plus grand k (le
+    /// moins de modifications par bit) qui tient à coup sûr
+    ///
+    /// Chaque coefficient d'amplitude 1 devient nul au plus une fois et n'est alors perdu
+    /// qu'une fois : l'insertion tient si les groupes ne demandent pas plus de coefficients
+    /// qu'il n'y en a d'amplitude 2 ou plus.
+    fn embed(&mut self, password: Option<&str>, data: &[u8]) -> Result<()> {
+        let len = data.len() as u64;
+        let guaranteed = self.ac_at_least(2);
+        let capacity = self.capacity();
+        let too_large = || stego_error(SteganographyError::TooLarge(capacity));
+        let k = (1..=MAX_MATRIX_K).rev()
+            .find(|&k| len <= u32::MAX as u64 && HEADER_BITS + matrix_cost(len, k) <= guaranteed)
+            .ok_or_else(too_large)?;
+        let mut walk = F5Walk::new(self, password);
+        walk.write(self, 1, &container_header(k, data))
+            .and_then(|_| walk.write(self, k, data))
+            .ok_or_else(too_large)
+    }
+
+    /// Relit le conteneur écrit par `embed`
+    fn extract(&self, password: Option<&str>) -> Result<Vec<u8>> {
+        let mut walk = F5Walk::new(self, password);
+        let header = walk.read(self, 1, STEGO_HEADER_SIZE)
+            .ok_or_else(|| stego_error(SteganographyError::NoHiddenData))?;
+        let (k, size) = parse_container_header(&header)?;
+        if !(1..=MAX_MATRIX_K).contains(&k) {
+            return Err(stego_error(SteganographyError::CorruptedData(format!("invalid matrix parameter {}", k))));
+        }
+        let data = Some(size)
+            .filter(|&size| HEADER_BITS + matrix_cost(size, k) <= self.ac_at_least(1))
+            .and_then(|size| walk.read(self, k, size as usize))
+            .ok_or_else(|| stego_error(SteganographyError::CorruptedData(format!("size {} exceeds the cover capacity", size))))?;
+        verify_container(&header, &data)?;
+        Ok(data)
+    }
+}
+
+/// Coefficients non nuls que demandent `len` octets par groupes de k bits, sans rétrécissement
+fn matrix_cost(len: u64, k: u8) -> u64 {
+    (len * 8).div_ceil(k as u64) * ((1 << k) - 1)
+}
+
+/// Parcours des coefficients AC non nuls dans l'ordre dérivé du mot de passe
+struct F5Walk {
+    order: SlotOrder,
+    total: u64,
+    next: u64,
+}
+
+impl F5Walk {
+    fn new(cover: &JpegCover, password: Option<&str>) -> Self {
+        let total = cover.ac_count();
+        Self { order: SlotOrder::new(password, total), total, next: 0 }
+    }
+
+    fn next_nonzero(&mut self, cover: &JpegCover) -> Option<u64> {
+        while self.next < self.total {
+            let position = self.order.slot(self.next);
+            self.next += 1;
+            if cover.ac(position) != 0 {
+                return Some(position);
+            }
+        }
+        None
+    }
+
+    /// Groupe des 2^k - 1 coefficients non nuls suivants et sa valeur : XOR des rangs (à
+    /// partir de 1) des coefficients d'amplitude impaire
+    fn group(&mut self, cover: &JpegCover, k: u8) -> Option<(Vec<u64>, usize)> {
+        let group = (0..(1usize << k) - 1).map(|_| self.next_nonzero(cover)).collect::<Option<Vec<_>>>()?;
+        let value = group.iter().enumerate()
+            .filter(|&(_, &position)| cover.ac(position) & 1 != 0)
+            .fold(0, |value, (rank, _)| value ^ (rank + 1));
+        Some((group, value))
+    }
+
+    /// Donne la valeur `value` au groupe suivant en baissant d'un au plus une amplitude ; un
+    /// coefficient devenu nul sort du groupe, qui est alors refait
+    fn embed(&mut self, cover: &mut JpegCover, k: u8, value: usize) -> Option<()> {
+        loop {
+            let start = self.next;
+            let (group, current) = self.group(cover, k)?;
+            if current == value {
+                return Some(());
+            }
+            let coefficient = cover.ac_mut(group[(current ^ value) - 1]);
+            *coefficient -= coefficient.signum();
+            if *coefficient != 0 {
+                return Some(());
+            }
+            self.next = start;
+        }
+    }
+
+    /// Insère `data` par groupes de k bits (bit de poids faible de chaque octet d'abord)
+    fn write(&mut self, cover: &mut JpegCover, k: u8, data: &[u8]) -> Option<()> {
+        let mut stream = data.iter().flat_map(|&byte| (0..8).map(move |bit| (byte >> bit) as usize & 1)).peekable();
+        while stream.peek().is_some() {
+            let value = stream.by_ref().take(k as usize).enumerate().fold(0, |value, (bit, b)| value | b << bit);
+            self.embed(cover, k, value)?;
+        }
+        Some(())
+    }
+
+    /// Lit `len` octets écrits par `write`
+    fn read(&mut self, cover: &JpegCover, k: u8, len: usize) -> Option<Vec<u8>> {
+        let mut data = vec![0u8; len];
+        let total = len * 8;
+        let mut position = 0;
+        while position < total {
+            let (_, value) = self.group(cover, k)?;
+            for bit in 0..k as usize {
+                if position < total {
+                    data[position / 8] |= (((value >> bit) & 1) as u8) << (position % 8);
+                    position += 1;
+                }
+            }
+        }
+        Some(data)
+    }
+}
+
+fn read_tables(mut segment: &[u8], tables: &mut [Option<HuffmanTable>]) -> Result<()> {
+    while !segment.is_empty() {
+        if segment.len() < 17 {
+            return Err(jpeg_error("invalid Huffman table"));
+        }
+        let (class, id) = ((segment[0] >> 4) as usize, (segment[0] & 15) as usize);
+        let total: usize = segment[1..17].iter().map(|&count| count as usize).sum();
+        if class > 1 || id > 3 || segment.len() < 17 + total {
+            return Err(jpeg_error("invalid Huffman table"));
+        }
+        tables[class * 4 + id] = Some(HuffmanTable::new(&segment[1..17], segment[17..17 + total].to_vec())?);
+        segment = &segment[17 + total..];
+    }
+    Ok(())
+}
+
+/// Fin des données entropiques : premier marqueur autre qu'un octet bourré ou un RSTn
+fn entropy_end(data: &[u8], mut position: usize) -> usize {
+    while position + 1 < data.len() {
+        if data[position] == 0xff && !matches!(data[position + 1], 0x00 | 0xd0..=0xd7) {
+            return position;
+        }
+        position += 1;
+    }
+    data.len()
+}
+
+/// Valeur signée de `size` bits lue dans le flux (`size` au plus `MAX_DC_CATEGORY`)
+fn extend(bits: i32, size: u8) -> i32 {
+    debug_assert!(size <= MAX_DC_CATEGORY);
+    if size == 0 {
+        0
+    } else if bits < 1 << (size - 1) {
+        bits - (1 << size) + 1
+    } else {
+        bits
+    }
+}
+
+/// Catégorie (nombre de bits) et bits écrits d'une valeur signée
+fn category(value: i32) -> (u8, u32) {
+    let size = (32 - value.unsigned_abs().leading_zeros()) as u8;
+    let bits = if value < 0 { value - 1 } else { value };
+    (size, bits as u32 & ((1u32 << size) - 1))
+}
+
+fn jpeg_error(message: &str) -> anyhow::Error {
+    stego_error(SteganographyError::Cover(message.into()))
+}
+
+impl Compressor {
+    /// Octets d'archive que peut cacher ce fichier JPEG
+    /// (capacité garantie malgré le rétrécissement ; une archive plus petite utilise
+    /// l'insertion matricielle et modifie moins de coefficients)
+    pub fn jpeg_capacity<P: AsRef<Path>>(&self, jpeg_path: P) -> Result<u64> {
+        Ok(JpegCover::parse(&fs::read(jpeg_path)?)?.capacity())
+    }
+
+    /// Vérifie que `path` désigne un JPEG (`.jpg` ou `.jpeg`), avant tout travail
+    pub fn check_jpeg_output<P: AsRef<Path>>(path: P) -> Result<()> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("jpg" | "jpeg") => Ok(()),
+            _ => Err(jpeg_error(&format!("output {} must be a JPEG file (.jpg or .jpeg)", path.display()))),
+        }
+    }
+
+    /// Cache une archive dans les coefficients DCT d'un JPEG (F5) ; renvoie le chemin
+    /// réellement écrit (voir `OverwritePolicy::Rename`)
+    pub fn hide_in_jpeg<P: AsRef<Path>>(&self, archive_path: P, jpeg_path: P, output_path: P) -> Result<PathBuf> {
+        Self::check_jpeg_output(output_path.as_ref())?;
+        if self.steganography_bits()? != 1 {
+            return Err(jpeg_error("JPEG embedding sets its own rate (matrix encoding); use 1 bit per slot"));
+        }
+        let mut archive_data = Vec::new();
+        ArchiveReader::open(archive_path.as_ref())?.read_to_end(&mut archive_data)?;
+        let mut cover = JpegCover::parse(&fs::read(jpeg_path)?)?;
+        cover.embed(self.options.password.as_deref(), &archive_data)?;
+
+        let mut output_file = AtomicFile::create(output_path.as_ref(), self.options.overwrite)?;
+        output_file.write_all(&cover.encode()?)?;
+        output_file.commit()
+    }
+
+    /// Renvoie le chemin réellement écrit (voir `OverwritePolicy::Rename`)
+    pub fn extract_from_jpeg<P: AsRef<Path>>(&self, jpeg_path: P, output_path: P) -> Result<PathBuf> {
+        let cover = JpegCover::parse(&fs::read(jpeg_path)?)?;
+        let archive_data = cover.extract(self.options.password.as_deref())?;
+
+        let mut output_file = AtomicFile::create(output_path.as_ref(), self.options.overwrite)?;
+        output_file.write_all(&archive_data)?;
+        output_file.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionError, CompressionOptions};
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageEncoder, RgbImage};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hide_in_jpeg_coefficients() -> Result<()> {
+        let dir = tempdir()?;
+        let cover = dir.path().join("photo.jpg");
+        let stego = dir.path().join("partage.jpg");
+        let archive = dir.path().join("hidden.ntk");
+        let extracted = dir.path().join("extracted.ntk");
+
+        let image = RgbImage::from_fn(320, 240, |x, y| {
+            image::Rgb([(x * 7 + y * 3) as u8, ((x ^ y) * 5) as u8, ((x * y) % 251) as u8])
+        });
+        JpegEncoder::new_with_quality(fs::File::create(&cover)?, 90)
+            .write_image(&image, 320, 240, image::ColorType::Rgb8)?;
+
+        // Sans modification, le réencodage redonne le même fichier
+        let original = fs::read(&cover)?;
+        assert_eq!(JpegCover::parse(&original)?.encode()?, original);
+
+        // Dimensions annoncées sans rapport avec la taille du fichier : refusé sans allouer
+        let mut oversized = original.clone();
+        let frame = oversized.windows(2).position(|w| w == [0xff, 0xc0]).unwrap();
+        oversized[frame + 5..frame + 9].copy_from_slice(&[0xff; 4]);
+        assert!(JpegCover::parse(&oversized).is_err_and(|e| e.to_string().contains("too large")));
+
+        let compressor = Compressor::new(CompressionOptions { password: Some("photo".into()), ..Default::default() });
+        let capacity = compressor.jpeg_capacity(&cover)?;
+        let mut data = vec![0u8; capacity as usize];
+        getrandom::getrandom(&mut data)?;
+        fs::write(&archive, &data)?;
+
+        compressor.hide_in_jpeg(&archive, &cover, &stego)?;
+        compressor.extract_from_jpeg(&stego, &extracted)?;
+        assert_eq!(fs::read(&extracted)?, data);
+        // Un JPEG toujours lisible
+        assert_eq!(image::open(&stego)?.to_rgb8().dimensions(), (320, 240));
+
+        // Plus de bits que de coefficients non nuls
+        let nonzero = JpegCover::parse(&original)?.ac_at_least(1);
+        fs::write(&archive, vec![1u8; nonzero as usize / 8 + 1])?;
+        assert!(compressor.hide_in_jpeg(&archive, &cover, &stego).is_err());
+
+        // Sortie sans extension JPEG : refusée avant tout travail
+        let png = dir.path().join("partage.png");
+        assert!(compressor.hide_in_jpeg(&archive, &cover, &png).is_err());
+        assert!(!png.exists());
+        let err = compressor.extract_from_jpeg(&cover, &extracted).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CompressionError>(),
+            Some(CompressionError::SteganographyError(SteganographyError::NoHiddenData))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_f5_keeps_histogram_shape() -> Result<()> {
+        let dir = tempdir()?;
+        let cover = dir.path().join("photo.jpg");
+        let stego = dir.path().join("partage.jpg");
+        let archive = dir.path().join("hidden.ntk");
+        let extracted = dir.path().join("extracted.ntk");
+
+        // Dégradés bruités : histogramme des amplitudes décroissant, comme une photo
+        let mut seed = 1u32;
+        let image = RgbImage::from_fn(320, 240, |x, y| {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let noise = seed >> 28;
+            image::Rgb([(x * 7 + y * 3 + noise) as u8, ((x ^ y) * 5 + noise) as u8, ((x * y) % 251 + noise) as u8])
+        });
+        JpegEncoder::new_with_quality(fs::File::create(&cover)?, 90)
+            .write_image(&image, 320, 240, image::ColorType::Rgb8)?;
+
+        let compressor = Compressor::new(CompressionOptions { password: Some("photo".into()), ..Default::default() });
+        let mut data = vec![0u8; compressor.jpeg_capacity(&cover)? as usize / 3];
+        getrandom::getrandom(&mut data)?;
+        fs::write(&archive, &data)?;
+        compressor.hide_in_jpeg(&archive, &cover, &stego)?;
+        compressor.extract_from_jpeg(&stego, &extracted)?;
+        assert_eq!(fs::read(&extracted)?, data);
+
+        // Amplitudes 1 à 7 des coefficients AC
+        let histogram = |path: &Path| -> Result<Vec<i64>> {
+            let cover = JpegCover::parse(&fs::read(path)?)?;
+            Ok((1..8).map(|a| cover.ac_at_least(a) as i64 - cover.ac_at_least(a + 1) as i64).collect())
+        };
+        let (before, after) = (histogram(&cover)?, histogram(&stego)?);
+        // JSteg égalise les paires (2k, 2k + 1) ; F5 garde un histogramme décroissant
+        assert!(after.windows(2).all(|pair| pair[0] > pair[1]));
+        for k in [1, 3, 5] {
+            assert!(after[k] - after[k + 1] > (before[k] - before[k + 1]) / 2);
+        }
+        // Insertion matricielle : moins de coefficients modifiés qu'avec un bit par coefficient
+        let original = JpegCover::parse(&fs::read(&cover)?)?;
+        let changed = |modified: &JpegCover| (0..original.ac_count()).filter(|&i| original.ac(i) != modified.ac(i)).count();
+        let mut plain = JpegCover::parse(&fs::read(&cover)?)?;
+        F5Walk::new(&plain, Some("photo")).write(&mut plain, 1, &data).unwrap();
+        let matrix = changed(&JpegCover::parse(&fs::read(&stego)?)?);
+        assert!(matrix < changed(&plain) * 3 / 4);
+        Ok(())
+    }
+}
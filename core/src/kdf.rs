@@ -0,0 +1,107 @@
+//! Dérivation de la clé de chiffrement à partir du mot de passe
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{CompressionError, KEY_SIZE};
+
+// Paramètres des archives produites avant l'enregistrement de la KDF dans l'en-tête
+const LEGACY_PBKDF2_ITERATIONS: u32 = 10_000;
+// Limite de mémoire acceptée à la lecture d'un en-tête (4 Gio)
+const MAX_MEMORY_KIB: u64 = 4 * 1024 * 1024;
+// Limites de coût en temps : un en-tête malveillant ne doit pas bloquer le processeur
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+const MAX_ARGON2_PARALLELISM: u32 = 64;
+const MAX_SCRYPT_RP: u64 = 1024;
+
+/// Algorithme de dérivation de clé et ses paramètres de coût
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "algorithm", rename_all = "lowercase")]
+pub enum KeyDerivation {
+    /// Argon2id (recommandé)
+    Argon2id {
+        /// Mémoire utilisée en Kio
+        memory_kib: u32,
+        /// Nombre de passes
+        iterations: u32,
+        /// Degré de parallélisme
+        parallelism: u32,
+    },
+    /// scrypt (N = 2^log_n)
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    /// PBKDF2-HMAC-SHA256
+    Pbkdf2 { iterations: u32 },
+}
+
+impl Default for KeyDerivation {
+    fn default() -> Self {
+        Self::Argon2id {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KeyDerivation {
+    /// Paramètres implicites des archives dont l'en-tête ne précise pas de KDF
+    pub const LEGACY: Self = Self::Pbkdf2 { iterations: LEGACY_PBKDF2_ITERATIONS };
+
+    pub(crate) fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Vec<u8>> {
+        self.validate()?;
+
+        let mut key = vec![0u8; KEY_SIZE];
+        match *self {
+            Self::Argon2id { memory_kib, iterations, parallelism } => {
+                let params = argon2::Params::new(memory_kib, iterations, parallelism, Some(KEY_SIZE))
+                    .map_err(|e| CompressionError::EncryptionError(format!("Invalid Argon2 parameters: {}", e)))?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(password.as_bytes(), salt, &mut key)
+                    .map_err(|e| CompressionError::EncryptionError(e.to_string()))?;
+            }
+            Self::Scrypt { log_n, r, p } => {
+                let params = scrypt::Params::new(log_n, r, p, KEY_SIZE)
+                    .map_err(|e| CompressionError::EncryptionError(format!("Invalid scrypt parameters: {}", e)))?;
+                scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+                    .map_err(|e| CompressionError::EncryptionError(e.to_string()))?;
+            }
+            Self::Pbkdf2 { iterations } => {
+                pbkdf2::pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, iterations, &mut key);
+            }
+        }
+        Ok(key)
+    }
+
+    /// Refuse les paramètres absurdes, notamment ceux lus depuis un en-tête malveillant
+    fn validate(&self) -> Result<()> {
+        let too_costly = match *self {
+            Self::Argon2id { iterations, parallelism, .. } => {
+                iterations > MAX_ARGON2_ITERATIONS || parallelism > MAX_ARGON2_PARALLELISM
+            }
+            Self::Scrypt { r, p, .. } => r as u64 * p as u64 > MAX_SCRYPT_RP,
+            Self::Pbkdf2 { iterations } => iterations > MAX_PBKDF2_ITERATIONS,
+        };
+        if too_costly {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+
+        let valid = match *self {
+            Self::Argon2id { memory_kib, iterations, parallelism } => {
+                iterations > 0 && parallelism > 0 && memory_kib as u64 <= MAX_MEMORY_KIB
+            }
+            // Mémoire utilisée par scrypt : 128 * r * N octets
+            Self::Scrypt { log_n, r, p } => {
+                log_n > 0 && log_n < 64 && r > 0 && p > 0
+                    && (128u128 * r as u128) << log_n <= (MAX_MEMORY_KIB as u128) * 1024
+            }
+            Self::Pbkdf2 { iterations } => iterations > 0,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(CompressionError::EncryptionError(format!("Unsupported key derivation parameters: {:?}", self)).into())
+        }
+    }
+}
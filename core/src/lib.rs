@@ -3,35 +3,98 @@
 //! This library provides the core compression and decompression functionality
 //! with support for encryption and parallel processing.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs::File;
-use std::io::{self, Read, Write, BufReader, BufWriter, Seek, SeekFrom};
+use std::io::{self, Read, Write, BufReader, Seek, SeekFrom};
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::sync::mpsc;
 use std::time::{SystemTime, Instant};
-use std::sync::{Arc, Mutex};
-use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use anyhow::{Result, Context};
+use anyhow::Result;
 use serde::{Serialize, Deserialize};
 use thiserror::Error;
-use rayon::prelude::*;
-use memmap2::Mmap;
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
-use bincode;
-use image::{ImageBuffer, Rgba, RgbaImage, GenericImageView, ImageFormat};
-use std::cmp::min;
+
+mod archive;
+mod audio;
+mod codec;
+mod dedup;
+mod dictionary;
+mod incremental;
+mod jpeg;
+mod kdf;
+mod output;
+mod parity;
+mod salvage;
+mod stego;
+mod stream;
+mod volume;
+
+pub use archive::{ArchiveEntry, EntryKind};
+pub use codec::{Codec, CodecPolicy};
+pub use dedup::Chunking;
+pub use kdf::KeyDerivation;
+pub use output::OverwritePolicy;
+pub use parity::RepairReport;
+pub use salvage::{ByteRange, GapFill, SalvageReport};
+pub use stream::{NtkReader, NtkWriter};
+use dedup::ChunkSequence;
+use dictionary::{DictionaryInfo, MAX_DICTIONARY_SIZE};
+use incremental::{BaseIndex, BaseInfo};
+use output::{AtomicDir, AtomicFile};
+use parity::{read_parity_record, ParityEncoder, ParityInfo, ParityReader};
+use stream::{BlockDecoder, Frame};
+use volume::{ArchiveOutput, ArchiveReader};
 
 // Constantes pour le format de fichier
 const MAGIC_BYTES: &[u8] = b"NTK1";
+const FORMAT_VERSION: u32 = 14;
 const HEADER_SIZE: usize = 512;
 const DEFAULT_BLOCK_SIZE: usize = 16 * 1024 * 1024; // 16MB
+// Taille de bloc maximale : une trame (jusqu'à 4 blocs) tient dans son préfixe u32
+const MAX_BLOCK_SIZE: usize = 1024 * 1024 * 1024;
 const SALT_SIZE: usize = 16;
 const KEY_SIZE: usize = 32;
 const NONCE_SIZE: usize = 12;
-const ENCRYPTED_CHUNK_SIZE: usize = 1024 * 1024; // 1MB
-const TAG_SIZE: usize = 16; // Taille du tag d'authentification AES-GCM
+// Version 1 : un seul nonce par fichier, réutilisé pour chaque bloc (à ne plus produire)
+const LEGACY_VERSION: u32 = 1;
+// Version 2+ : préfixe aléatoire par fichier, complété par l'index du bloc
+const NONCE_PREFIX_SIZE: usize = 8;
+// Version 3+ : en-tête, index du bloc et marqueur de fin authentifiés (données associées)
+const VERSION_AUTHENTICATED: u32 = 3;
+// Version 6+ : trame de fin (taille 0), index des blocs et trailer en fin de fichier
+const VERSION_BLOCK_INDEX: u32 = 6;
+const FOOTER_MAGIC: &[u8] = b"NTKX";
+const TRAILER_SIZE: u64 = 12; // taille de l'index (u64 LE) + FOOTER_MAGIC
+// Index de nonce réservé au scellement de l'index des blocs
+const FOOTER_NONCE_INDEX: u32 = u32::MAX;
+// Version 7+ : chaque bloc commence par l'identifiant de son codec
+const VERSION_CODEC_TAG: u32 = 7;
+// Version 9+ : empreintes BLAKE3 du contenu et de chaque bloc dans l'index
+const VERSION_CHECKSUMS: u32 = 9;
+// Version 10+ : parité Reed-Solomon optionnelle entre la trame de fin et l'index
+const VERSION_PARITY: u32 = 10;
+// Version 11+ : chaque trame commence par un marqueur de synchronisation, son index et la
+// position de son contenu dans les données d'origine (reprise après une zone illisible)
+const VERSION_SYNC: u32 = 11;
+const SYNC_MARKER: &[u8] = b"\x89NTKSYNC";
+const SYNC_HEADER_SIZE: usize = 20; // marqueur + index (u32 LE) + position (u64 LE)
+// Version 12+ : déduplication optionnelle, l'index liste la suite des blocs à restituer
+const VERSION_DEDUP: u32 = 12;
+// Version 13+ : archive incrémentale, l'en-tête désigne une archive de base et l'index
+// peut renvoyer à des éléments de sa suite
+const VERSION_INCREMENTAL: u32 = 13;
+// Version 14+ : la parité protège aussi l'index ; ses paramètres et empreintes sont écrits
+// en clair avant et après la section de parité, plus dans l'index
+const VERSION_PARITY_RECORD: u32 = 14;
+// Taille maximale acceptée pour l'index des blocs lu en flux
+const MAX_FOOTER_SIZE: u64 = 256 * 1024 * 1024;
 
 #[derive(Debug, Error)]
 pub enum CompressionError {
@@ -39,32 +102,103 @@ pub enum CompressionError {
     InvalidFormat,
     #[error("Encryption error: {0}")]
     EncryptionError(String),
+    #[error("Authentication failed: {0}")]
+    AuthenticationError(String),
+    #[error("Integrity check failed: {0}")]
+    IntegrityError(String),
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
     #[error("Compression error: {0}")]
     CompressionError(String),
+    // Sans `#[from]` : le détail est déjà dans le message, pas de cause répétée par `{:#}`
     #[error("Steganography error: {0}")]
-    SteganographyError(String),
+    SteganographyError(SteganographyError),
+    #[error("Operation cancelled")]
+    Cancelled,
+}
+
+/// Cas d'erreur de la stéganographie : le support ne contient rien, ou des données abîmées
+#[derive(Debug, Error)]
+pub enum SteganographyError {
+    /// Pas de conteneur NTK (ou mauvais mot de passe : l'ordre des emplacements diffère)
+    #[error("no hidden data found")]
+    NoHiddenData,
+    #[error("hidden data is corrupted: {0}")]
+    CorruptedData(String),
+    #[error("unsupported hidden data version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Archive too large for this cover. Max size: {0} bytes")]
+    TooLarge(u64),
+    /// Support illisible, format de sortie ou paramètres invalides
+    #[error("{0}")]
+    Cover(String),
+}
+
+impl From<SteganographyError> for CompressionError {
+    fn from(e: SteganographyError) -> Self {
+        CompressionError::SteganographyError(e)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionOptions {
     /// Niveau de compression (1-22 pour zstd)
     pub level: u32,
+    /// Algorithme de compression des blocs
+    #[serde(default)]
+    pub codec: Codec,
+    /// Compromis vitesse / taux du codec automatique
+    #[serde(default)]
+    pub codec_policy: CodecPolicy,
     /// Taille des blocs en octets
     pub block_size: usize,
+    /// Découpage en blocs de taille fixe ou selon le contenu (déduplication)
+    #[serde(default)]
+    pub chunking: Chunking,
     /// Nombre de threads à utiliser
     pub threads: usize,
-    /// Taille du dictionnaire
+    /// Nombre maximal de blocs en mémoire pendant la compression ou la décompression
+    /// (0 : `threads`)
+    #[serde(default)]
+    pub in_flight_blocks: usize,
+    /// Comportement si la sortie existe déjà
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+    /// Redondance Reed-Solomon en pourcentage des données (0 : aucune parité)
+    #[serde(default)]
+    pub parity_percent: u8,
+    /// Taille maximale d'un volume : l'archive est écrite en `nom.ntk.001`, `.002`…
+    /// (0 : un seul fichier)
+    #[serde(default)]
+    pub volume_size: u64,
+    /// Taille maximale d'un dictionnaire entraîné
     pub dictionary_size: usize,
+    /// Dictionnaire zstd à utiliser (compression, et lecture des archives qui y font référence)
+    #[serde(default)]
+    pub dictionary_path: Option<String>,
+    /// Ne pas intégrer le dictionnaire à l'archive : seuls son identifiant et son empreinte y sont enregistrés
+    #[serde(default)]
+    pub external_dictionary: bool,
+    /// Archive de base : seuls les blocs absents de sa chaîne sont stockés (archive
+    /// incrémentale). À la lecture, remplace la base enregistrée, cherchée sinon à côté
+    /// de l'archive.
+    #[serde(default)]
+    pub base_archive: Option<String>,
     /// Utiliser le chiffrement
     pub use_encryption: bool,
     /// Mot de passe pour le chiffrement
     pub password: Option<String>,
+    /// Fonction de dérivation de clé et ses paramètres
+    #[serde(default)]
+    pub key_derivation: KeyDerivation,
     /// Utiliser la stéganographie
     pub use_steganography: bool,
     /// Chemin de l'image pour la stéganographie
     pub steganography_image: Option<String>,
+    /// Bits de poids faible utilisés par canal de couleur ou échantillon audio (1 ou 2,
+    /// 0 : 1) ; l'ordre des emplacements est dérivé de `password`
+    #[serde(default)]
+    pub steganography_bits: u8,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +211,9 @@ pub struct FileMetadata {
     pub creation_time: u64,
     pub checksum: String,
     pub estimated_time: f64,
+    /// Fichier réellement écrit (voir `OverwritePolicy::Rename`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,6 +223,277 @@ struct FileHeader {
     encrypted: bool,
     original_name: String,
     original_size: u64,
+    /// KDF utilisée ; absente des anciennes archives (PBKDF2, 10 000 itérations)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf: Option<KeyDerivation>,
+    /// Archive multi-fichiers : le flux décompressé commence par la table des matières
+    #[serde(default, skip_serializing_if = "is_false")]
+    multi_entry: bool,
+    /// Dictionnaire zstd ; s'il est intégré, il suit le sel et le préfixe du nonce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dictionary: Option<DictionaryInfo>,
+    /// Blocs dédupliqués : la suite à restituer est dans l'index (lecture non séquentielle)
+    #[serde(default, skip_serializing_if = "is_false")]
+    deduplicated: bool,
+    /// Archive incrémentale : base dont la suite des blocs est référencée
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<BaseInfo>,
+    /// Taille des blocs à l'écriture, qui borne les trames lues ; absente des anciennes
+    /// archives (celle des options du lecteur)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block_size: Option<u64>,
+}
+
+/// Index des blocs, écrit après la dernière trame
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ArchiveFooter {
+    blocks: Vec<BlockIndexEntry>,
+    /// Empreinte BLAKE3 (hex) des données d'origine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    /// Parité de la zone des trames (v10 à v13 ; v14+ : lue dans son enregistrement)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parity: Option<ParityInfo>,
+    /// Index des blocs formant les données d'origine, dans l'ordre ; un bloc peut y
+    /// figurer plusieurs fois (v12+, déduplication). Absent : chaque bloc une fois.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chunks: Option<Vec<u32>>,
+    /// Taille des données d'origine, quand l'en-tête ne pouvait pas la connaître
+    /// (écriture en flux, où elle y vaut 0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    original_size: Option<u64>,
+}
+
+/// Élément de la suite des blocs : bloc stocké dans l'archive, ou élément de la suite
+/// de l'archive de base
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ChunkRef {
+    Block(usize),
+    Base(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BlockIndexEntry {
+    /// Position de la trame (préfixe de longueur compris) dans l'archive
+    offset: u64,
+    /// Taille du bloc compressé, sans le préfixe
+    compressed_size: u32,
+    /// Taille du bloc décompressé
+    size: u64,
+    /// Empreinte BLAKE3 (hex) du bloc décompressé
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+impl ArchiveFooter {
+    fn verify_block(&self, index: usize, hash: &blake3::Hash) -> Result<()> {
+        let expected = self.blocks.get(index).and_then(|entry| entry.hash.as_deref());
+        if expected != Some(hash.to_hex().as_str()) {
+            return Err(CompressionError::IntegrityError(format!("block {} checksum mismatch", index)).into());
+        }
+        Ok(())
+    }
+
+    /// Suite des blocs formant les données d'origine
+    fn sequence(&self) -> Vec<ChunkRef> {
+        match &self.chunks {
+            Some(chunks) => chunks.iter()
+                .map(|&index| match (index as usize).checked_sub(self.blocks.len()) {
+                    Some(entry) => ChunkRef::Base(entry),
+                    None => ChunkRef::Block(index as usize),
+                })
+                .collect(),
+            None => (0..self.blocks.len()).map(ChunkRef::Block).collect(),
+        }
+    }
+
+    /// Suite des blocs avec la position d'origine et la taille de chacun ; `base` : taille
+    /// des éléments de la suite de l'archive de base
+    fn layout(&self, base: &[u64]) -> Result<Vec<(ChunkRef, u64, u64)>> {
+        let mut position = 0u64;
+        self.sequence().into_iter().map(|chunk| {
+            let size = match chunk {
+                ChunkRef::Block(index) => self.blocks[index].size,
+                ChunkRef::Base(entry) => *base.get(entry).ok_or(CompressionError::InvalidFormat)?,
+            };
+            let start = position;
+            position += size;
+            Ok((chunk, start, size))
+        }).collect()
+    }
+
+    /// Position d'origine de la première occurrence de chaque bloc stocké
+    fn block_starts(&self, base: &[u64]) -> Result<Vec<u64>> {
+        let mut starts = Vec::with_capacity(self.blocks.len());
+        for (chunk, start, _) in self.layout(base)? {
+            if chunk == ChunkRef::Block(starts.len()) {
+                starts.push(start);
+            }
+        }
+        Ok(starts)
+    }
+
+    /// Chaque bloc stocké apparaît dans la suite, pour la première fois dans l'ordre de
+    /// l'archive ; les index suivants désignent la base (vérifiés à son ouverture)
+    fn validate_chunks(&self) -> Result<()> {
+        let Some(chunks) = &self.chunks else {
+            return Ok(());
+        };
+        let mut stored = 0usize;
+        for &index in chunks.iter().filter(|&&index| (index as usize) < self.blocks.len()) {
+            match (index as usize).cmp(&stored) {
+                std::cmp::Ordering::Less => {}
+                std::cmp::Ordering::Equal => stored += 1,
+                std::cmp::Ordering::Greater => return Err(CompressionError::InvalidFormat.into()),
+            }
+        }
+        if stored != self.blocks.len() {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        Ok(())
+    }
+
+    fn verify_checksum(&self, hash: &blake3::Hash) -> Result<()> {
+        if self.checksum.as_deref() != Some(hash.to_hex().as_str()) {
+            return Err(CompressionError::IntegrityError("content checksum mismatch".into()).into());
+        }
+        Ok(())
+    }
+}
+
+/// Écrit les trames de blocs en construisant l'index et les empreintes
+struct FrameWriter<W: Write> {
+    writer: W,
+    position: u64,
+    /// Position dans les données d'origine
+    data_position: u64,
+    index: Vec<BlockIndexEntry>,
+    hasher: blake3::Hasher,
+    parity: Option<ParityEncoder>,
+    /// Suite des blocs formant les données d'origine (déduplication)
+    chunks: Option<Vec<ChunkRef>>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// `parity_percent` : redondance de la parité (0 : aucune)
+    fn new(writer: W, position: u64, parity_percent: u8) -> Result<Self> {
+        let parity = match parity_percent {
+            0 => None,
+            percent => Some(ParityEncoder::new(percent, position)?),
+        };
+        Ok(Self {
+            writer,
+            position,
+            data_position: 0,
+            index: Vec::new(),
+            hasher: blake3::Hasher::new(),
+            parity,
+            chunks: None,
+        })
+    }
+
+    /// `data` : contenu d'origine du bloc, qui suit les précédents
+    fn write_block(&mut self, block: &[u8], data: &[u8]) -> Result<()> {
+        self.write_unique_block(block, data, self.data_position)?;
+        self.hasher.update(data);
+        self.data_position += data.len() as u64;
+        Ok(())
+    }
+
+    /// Écrit la trame d'un bloc dont le contenu commence à `position` dans les données
+    /// d'origine ; la suite des blocs est enregistrée à part (`add_chunk`)
+    fn write_unique_block(&mut self, block: &[u8], data: &[u8], position: u64) -> Result<()> {
+        self.write_prefix(block.len() as u32, position)?;
+        self.write_frame(block)?;
+        self.index.push(BlockIndexEntry {
+            offset: self.position,
+            compressed_size: block.len() as u32,
+            size: data.len() as u64,
+            hash: Some(blake3::hash(data).to_hex().to_string()),
+        });
+        self.position += block.len() as u64 + frame_prefix_size(FORMAT_VERSION);
+        Ok(())
+    }
+
+    /// Ajoute un bloc (nouveau, déjà stocké ou de la base) à la suite des données d'origine
+    fn add_chunk(&mut self, chunk: ChunkRef, data: &[u8]) {
+        self.hasher.update(data);
+        self.data_position += data.len() as u64;
+        self.chunks.get_or_insert_with(Vec::new).push(chunk);
+    }
+
+    /// Marqueur de synchronisation, index, position d'origine et longueur de la trame suivante
+    fn write_prefix(&mut self, size: u32, position: u64) -> Result<()> {
+        let mut prefix = Vec::with_capacity(frame_prefix_size(FORMAT_VERSION) as usize);
+        prefix.extend_from_slice(SYNC_MARKER);
+        prefix.extend_from_slice(&(self.index.len() as u32).to_le_bytes());
+        prefix.extend_from_slice(&position.to_le_bytes());
+        prefix.extend_from_slice(&size.to_le_bytes());
+        self.write_frame(&prefix)
+    }
+
+    fn write_frame(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        if let Some(parity) = &mut self.parity {
+            parity.update(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Écrit la trame de fin ; renvoie l'index à écrire avec `finish`
+    fn end_blocks(&mut self) -> Result<ArchiveFooter> {
+        self.write_prefix(0, self.data_position)?;
+        self.position += frame_prefix_size(FORMAT_VERSION);
+
+        Ok(ArchiveFooter {
+            blocks: self.index.clone(),
+            checksum: Some(self.hasher.finalize().to_hex().to_string()),
+            parity: None,
+            chunks: self.chunks.as_ref().map(|chunks| {
+                chunks.iter().map(|chunk| match *chunk {
+                    ChunkRef::Block(index) => index as u32,
+                    ChunkRef::Base(entry) => (self.index.len() + entry) as u32,
+                }).collect()
+            }),
+            original_size: None,
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Écrit l'index (éventuellement scellé), la parité et le trailer ; renvoie la taille
+    /// totale de l'archive
+    fn finish(&mut self, footer: &[u8]) -> Result<u64> {
+        self.write_frame(footer)?;
+        self.position += footer.len() as u64;
+        if let Some(encoder) = self.parity.take() {
+            self.position += encoder.finish(&mut self.writer, footer.len() as u64)?;
+        }
+        self.writer.write_all(&(footer.len() as u64).to_le_bytes())?;
+        self.writer.write_all(FOOTER_MAGIC)?;
+        self.writer.flush()?;
+        Ok(self.position + TRAILER_SIZE)
+    }
+
+    fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// En-tête lu depuis une archive, suivi des paramètres de chiffrement
+#[derive(Clone)]
+struct ParsedHeader {
+    header: FileHeader,
+    salt: Vec<u8>,
+    nonce: Vec<u8>,
+    /// Dictionnaire intégré à l'archive
+    dictionary: Option<Vec<u8>>,
+    /// Octets bruts (JSON + sel + nonce + dictionnaire), liés à chaque bloc comme données associées
+    raw: Vec<u8>,
+    /// Chemin de l'archive, d'où est cherchée sa base
+    location: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone)]
@@ -98,28 +506,59 @@ pub struct ProgressInfo {
 
 pub type ProgressCallback = Arc<Mutex<dyn FnMut(ProgressInfo) + Send + 'static>>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct EncryptedBlock {
-    nonce: Vec<u8>,
-    data: Vec<u8>,
+/// Jeton d'annulation partagé entre l'appelant et une opération en cours,
+/// consulté entre deux blocs
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
+#[derive(Clone)]
 pub struct Compressor {
     options: CompressionOptions,
-    progress_callback: Option<Arc<Mutex<dyn FnMut(ProgressInfo) + Send + 'static>>>,
+    progress_callback: Option<ProgressCallback>,
+    cancellation: Option<CancellationToken>,
+    /// Dictionnaire zstd, fourni ou chargé depuis `options.dictionary_path`
+    dictionary: OnceLock<Option<Vec<u8>>>,
+    /// Suite des blocs de `options.base_archive`
+    base_index: OnceLock<Option<BaseIndex>>,
 }
 
 impl Default for CompressionOptions {
     fn default() -> Self {
         Self {
             level: 19,
+            codec: Codec::default(),
+            codec_policy: CodecPolicy::default(),
             block_size: DEFAULT_BLOCK_SIZE,
+            chunking: Chunking::default(),
             threads: num_cpus::get(),
-            dictionary_size: 64 * 1024 * 1024,
+            in_flight_blocks: 0,
+            overwrite: OverwritePolicy::default(),
+            parity_percent: 0,
+            volume_size: 0,
+            dictionary_size: 112 * 1024,
+            dictionary_path: None,
+            external_dictionary: false,
+            base_archive: None,
             use_encryption: false,
             password: None,
+            key_derivation: KeyDerivation::default(),
             use_steganography: false,
             steganography_image: None,
+            steganography_bits: 0,
         }
     }
 }
@@ -129,6 +568,9 @@ impl Compressor {
         Self {
             options,
             progress_callback: None,
+            cancellation: None,
+            dictionary: OnceLock::new(),
+            base_index: OnceLock::new(),
         }
     }
 
@@ -139,82 +581,55 @@ impl Compressor {
         self.progress_callback = Some(Arc::new(Mutex::new(callback)));
     }
 
+    /// Les opérations s'arrêtent avec `CompressionError::Cancelled` dès que le jeton est
+    /// annulé ; le fichier de sortie partiel est supprimé
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<()> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(CompressionError::Cancelled.into()),
+            _ => Ok(()),
+        }
+    }
+
     pub fn compress<P: AsRef<Path>>(&self, input: P, output: P) -> Result<FileMetadata> {
         let start = Instant::now();
         let input_path = input.as_ref();
         let output_path = output.as_ref();
 
-        let input_file = File::open(input_path)?;
-        let mut output_file = BufWriter::new(File::create(output_path)?);
+        // Un dossier devient une archive multi-fichiers
+        if input_path.is_dir() {
+            return self.compress_entries(&[input_path], output_path);
+        }
+
+        let mut input_file = File::open(input_path)?;
+        let mut output_file = self.create_output(output_path)?;
 
         let input_size = input_file.metadata()?.len();
-        let input_name = input_path.file_name()
-            .ok_or_else(|| CompressionError::IoError(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Invalid input filename"
-            )))?
-            .to_string_lossy()
-            .into_owned();
+        let input_name = file_name(input_path)?;
 
         // Préparer le chiffrement si nécessaire
-        let (key, salt, nonce) = if self.options.use_encryption {
+        let (key, salt, nonce_prefix) = if self.options.use_encryption {
             self.prepare_encryption()?
         } else {
             (vec![], vec![], vec![])
         };
 
-        // Mapper le fichier en mémoire pour une lecture efficace
-        let mmap = unsafe { Mmap::map(&input_file)? };
+        // L'en-tête est construit avant les blocs car il est authentifié avec chacun d'eux
+        let header = self.new_header(&input_name, input_size)?;
+        let header_bytes = self.encode_header(&header, &salt, &nonce_prefix)?;
+        output_file.write_all(&header_bytes)?;
 
-        // Diviser le fichier en blocs
-        let chunk_size = self.options.block_size;
-        let chunks: Vec<_> = mmap.chunks(chunk_size).collect();
-        
-        // Compresser les blocs en parallèle
-        let processed_bytes = Arc::new(Mutex::new(0u64));
-        let start_time = Arc::new(Instant::now());
-
-        let compressed_blocks: Vec<_> = chunks.par_iter()
-            .map(|chunk| {
-                let result = self.compress_block(chunk, &key, &nonce);
-                
-                // Mise à jour de la progression
-                if let Some(ref callback) = &self.progress_callback {
-                    let mut bytes = processed_bytes.lock().unwrap();
-                    *bytes += chunk.len() as u64;
-                    
-                    let elapsed = start_time.elapsed().as_secs_f64();
-                    let speed = *bytes as f64 / elapsed;
-                    let remaining = (input_size - *bytes) as f64 / speed;
-                    
-                    if let Ok(mut guard) = callback.lock() {
-                        guard(ProgressInfo {
-                            processed_bytes: *bytes,
-                            total_bytes: input_size,
-                            current_speed: speed,
-                            estimated_remaining_time: remaining,
-                        });
-                    }
-                }
-                
-                result
-            })
-            .collect::<Result<_>>()?;
-
-        // Écrire l'en-tête
-        self.write_header(&mut output_file, &input_name, input_size, &salt, &nonce)?;
-
-        let mut compressed_size = HEADER_SIZE as u64;
-        for block in compressed_blocks {
-            let block_size = block.len() as u32;
-            output_file.write_all(&block_size.to_le_bytes())?;
-            output_file.write_all(&block)?;
-            compressed_size += block_size as u64 + 4;
-        }
+        // Le fichier est lu au fil de la compression : la mémoire reste bornée
+        let mut frames = FrameWriter::new(output_file, header_bytes.len() as u64, self.options.parity_percent)?;
+        self.compress_stream(&mut input_file, &[input_size], &header_bytes, &key, &nonce_prefix, &mut frames)?;
 
-        output_file.flush()?;
+        let footer = frames.end_blocks()?;
+        let compressed_size = frames.finish(&self.seal_footer(&footer, &header_bytes, &key, &nonce_prefix)?)?;
+        let output_path = frames.into_inner().commit()?;
 
-        let checksum = blake3::hash(&mmap);
         let elapsed = start.elapsed().as_secs_f64();
 
         Ok(FileMetadata {
@@ -226,135 +641,525 @@ impl Compressor {
             creation_time: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .as_secs(),
-            checksum: hex::encode(checksum.as_bytes()),
+            checksum: footer.checksum.unwrap_or_default(),
             estimated_time: elapsed,
+            output_path: Some(output_path.to_string_lossy().into_owned()),
         })
     }
 
-    pub fn decompress<P: AsRef<Path>>(&self, input: P, output: P) -> Result<()> {
+    /// Renvoie le chemin réellement écrit (voir `OverwritePolicy::Rename`)
+    pub fn decompress<P: AsRef<Path>>(&self, input: P, output: P) -> Result<PathBuf> {
         let input_path = input.as_ref();
         let output_path = output.as_ref();
 
-        let mut input_file = BufReader::new(File::open(input_path)?);
-        let mut output_file = BufWriter::new(File::create(output_path)?);
-
         // Lire et vérifier l'en-tête
-        let (header, salt, nonce) = self.read_header(&mut input_file)?;
+        let (mut input_file, parsed) = self.open_archive(input_path)?;
+
+        // Une archive multi-fichiers est extraite dans le dossier de sortie, sous un nom
+        // temporaire tant qu'elle n'est pas complète
+        if parsed.header.multi_entry {
+            let output_dir = AtomicDir::create(output_path, self.options.overwrite)?;
+            self.extract_into(&parsed, &mut input_file, output_dir.path(), &[])?;
+            return output_dir.commit();
+        }
 
-        // Préparer le déchiffrement si nécessaire
-        let key = if header.encrypted {
-            if let Some(password) = &self.options.password {
-                self.derive_key(password, &salt)?
-            } else {
-                return Err(CompressionError::EncryptionError("Password required for encrypted file".into()).into());
+        let mut output_file = AtomicFile::create(output_path, self.options.overwrite)?;
+        self.decode_blocks(&parsed, &mut input_file, |offset, data| {
+            // Écrire le bloc décompressé
+            if let Err(e) = output_file.write_all(data) {
+                return Err(CompressionError::IoError(io::Error::new(
+                    e.kind(),
+                    format!("Failed to write decompressed block at offset {}: {}", offset, e)
+                )).into());
             }
+            Ok(ControlFlow::Continue(()))
+        })?;
+
+        output_file.commit()
+    }
+
+    /// Vérifie une archive (authentification, format et empreintes) sans rien écrire
+    pub fn verify<P: AsRef<Path>>(&self, archive: P) -> Result<()> {
+        let (mut input, parsed) = self.open_archive(archive.as_ref())?;
+        self.decode_blocks(&parsed, &mut input, |_, _| Ok(ControlFlow::Continue(())))
+    }
+
+    /// Lit, déchiffre et décompresse les blocs dans l'ordre, en passant chacun à `on_block`
+    /// avec la position de sa trame ; `on_block` peut interrompre la lecture
+    fn decode_blocks<R, F>(&self, parsed: &ParsedHeader, input: &mut R, mut on_block: F) -> Result<()>
+    where
+        R: Read + Seek,
+        F: FnMut(u64, &[u8]) -> Result<ControlFlow<()>>,
+    {
+        let key = self.archive_key(parsed)?;
+        // Avec parité, chaque fragment est vérifié et reconstruit au besoin pendant la lecture ;
+        // avec déduplication, les blocs déjà lus sont relus là où la suite les référence
+        let footer = if parsed.header.version >= VERSION_PARITY {
+            Some(self.read_footer(parsed, &key, input)?)
         } else {
-            vec![]
+            None
         };
+        let parity = footer.as_ref().and_then(|footer| footer.parity.clone());
+        let mut references = match &footer {
+            Some(footer) => ChunkSequence::new(self, parsed, &key, footer)?,
+            None => None,
+        };
+        let input = &mut ParityReader::new(input, parity, false);
 
-        let file_size = input_file.seek(SeekFrom::End(0))?;
-        input_file.seek(SeekFrom::Start(HEADER_SIZE as u64))?;
+        let data_start = parsed.raw.len() as u64;
+        let file_size = input.seek(SeekFrom::End(0))?;
+        input.seek(SeekFrom::Start(data_start))?;
 
-        let mut processed_bytes = 0u64;
+        let mut decoder = BlockDecoder::new(parsed.clone(), key.clone(), input)?;
+        let window = self.in_flight_blocks();
         let start = Instant::now();
 
-        // Lire et décompresser les blocs
-        while processed_bytes < file_size - HEADER_SIZE as u64 {
-            // Lire la taille du bloc
-            let mut size_buf = [0u8; 4];
-            if let Err(e) = input_file.read_exact(&mut size_buf) {
-                if e.kind() == io::ErrorKind::UnexpectedEof {
-                    break;
+        // Les trames sont lues dans l'ordre, décodées en parallèle (au plus `window` à la
+        // fois, en comptant celles en attente) puis transmises dans l'ordre
+        rayon::in_place_scope(|scope| {
+            let (sender, receiver) = mpsc::channel::<(u32, Frame, Result<Vec<u8>>)>();
+            let mut ready = BTreeMap::new();
+            let mut read = 0u32;
+            let mut emitted = 0u32;
+            let mut reading = true;
+
+            loop {
+                // Fenêtre pleine (ou toutes les trames lues) : transmettre le bloc suivant
+                while emitted < read && (read - emitted >= window || !reading) {
+                    while !ready.contains_key(&emitted) {
+                        let (index, frame, result) = receiver.recv()
+                            .map_err(|_| CompressionError::CompressionError("Decompression worker stopped".into()))?;
+                        ready.insert(index, (frame, result));
+                    }
+                    let (frame, result): (Frame, Result<Vec<u8>>) = ready.remove(&emitted).unwrap();
+                    let decompressed = result?;
+                    if let Some(references) = &mut references {
+                        if references.emit(input, &mut decoder, Some(emitted), &mut on_block)?.is_break() {
+                            return Ok(());
+                        }
+                    }
+                    decoder.accept(&frame, &decompressed)?;
+                    emitted += 1;
+
+                    let flow = on_block(frame.offset, &decompressed)?;
+
+                    // Mise à jour de la progression
+                    let processed_bytes = frame.offset + frame.data.len() as u64 + frame_prefix_size(parsed.header.version);
+                    self.report_progress(processed_bytes, file_size - data_start, start);
+
+                    if flow.is_break() {
+                        return Ok(());
+                    }
+                }
+                if !reading {
+                    if let Some(references) = &mut references {
+                        if references.emit(input, &mut decoder, None, &mut on_block)?.is_break() {
+                            return Ok(());
+                        }
+                    }
+                    return decoder.finish(self, input);
+                }
+
+                match decoder.next_frame(self, input)? {
+                    Some(frame) => {
+                        let sender = sender.clone();
+                        let key = &key;
+                        scope.spawn(move |_| {
+                            let result = self.open_frame(parsed, key, &frame);
+                            // Le récepteur a disparu si la lecture a été interrompue
+                            let _ = sender.send((frame.index, frame, result));
+                        });
+                        read += 1;
+                    }
+                    None => reading = false,
                 }
-                return Err(e.into());
             }
-            let block_size = u32::from_le_bytes(size_buf);
+        })
+    }
 
-            // Vérification de sécurité sur la taille du bloc
-            if block_size == 0 || block_size as usize > self.options.block_size * 4 {
-                return Err(CompressionError::InvalidFormat.into());
+    /// Nombre maximal de blocs en cours de traitement (`threads` par défaut)
+    fn in_flight_blocks(&self) -> u32 {
+        match self.options.in_flight_blocks {
+            0 => self.options.threads.max(1) as u32,
+            blocks => blocks as u32,
+        }
+    }
+
+    /// Déchiffre et décompresse un bloc avec le nonce et les données associées de sa position
+    fn open_block(&self, parsed: &ParsedHeader, key: &[u8], index: u32, is_final: bool, block: &[u8]) -> Result<Vec<u8>> {
+        let header = &parsed.header;
+        let aad = if header.encrypted && header.version >= VERSION_AUTHENTICATED {
+            block_aad(&parsed.raw, index, is_final)
+        } else {
+            Vec::new()
+        };
+
+        // Les archives v1 réutilisent le même nonce pour tous les blocs
+        let nonce = if header.version == LEGACY_VERSION {
+            parsed.nonce.clone()
+        } else {
+            block_nonce(&parsed.nonce, index)
+        };
+
+        let dictionary = self.archive_dictionary(parsed)?;
+        self.decompress_block(block, key, &nonce, &aad, header.version >= VERSION_CODEC_TAG, dictionary)
+    }
+
+    /// Décode une trame lue par `BlockDecoder`
+    fn open_frame(&self, parsed: &ParsedHeader, key: &[u8], frame: &Frame) -> Result<Vec<u8>> {
+        self.open_block(parsed, key, frame.index, frame.is_final, &frame.data)
+            .map_err(|e| block_error(e, frame.index, frame.offset))
+    }
+
+    /// Clé de l'archive, dérivée avec la KDF enregistrée dans l'en-tête
+    fn archive_key(&self, parsed: &ParsedHeader) -> Result<Vec<u8>> {
+        if !parsed.header.encrypted {
+            return Ok(vec![]);
+        }
+        match &self.options.password {
+            Some(password) => parsed.header.kdf
+                .unwrap_or(KeyDerivation::LEGACY)
+                .derive_key(password, &parsed.salt),
+            None => Err(CompressionError::EncryptionError("Password required for encrypted file".into()).into()),
+        }
+    }
+
+    /// Lit `len` octets à partir de `offset` dans les données d'origine, en ne
+    /// décompressant que les blocs concernés. La plage est tronquée à la fin des données.
+    pub fn read_range<P: AsRef<Path>>(&self, archive: P, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let (mut input, parsed) = self.open_archive(archive.as_ref())?;
+        let end = offset.saturating_add(len as u64);
+        let mut range = Vec::with_capacity(len.min(self.options.block_size * 4));
+
+        // Sans index, les blocs précédant la plage doivent être décompressés
+        if parsed.header.version < VERSION_BLOCK_INDEX {
+            let mut block_start = 0u64;
+            self.decode_blocks(&parsed, &mut input, |_, data| {
+                append_range(&mut range, data, block_start, offset, end);
+                block_start += data.len() as u64;
+                Ok(if block_start >= end { ControlFlow::Break(()) } else { ControlFlow::Continue(()) })
+            })?;
+            return Ok(range);
+        }
+
+        let key = self.archive_key(&parsed)?;
+        let footer = self.read_footer(&parsed, &key, &mut input)?;
+        let mut base = self.open_base(&parsed)?;
+        let base_sizes = base.as_ref().map(|base| base.sizes()).unwrap_or_default();
+        let starts = footer.block_starts(&base_sizes)?;
+        let mut input = ParityReader::new(input, footer.parity.clone(), false);
+
+        // Blocs dans l'ordre des données d'origine, avec leur position
+        for (chunk, block_start, size) in footer.layout(&base_sizes)? {
+            let block_end = block_start + size;
+            if block_end > offset && block_start < end {
+                let data = match (chunk, &mut base) {
+                    (ChunkRef::Block(index), _) => {
+                        self.read_indexed_block(&parsed, &key, &footer, &mut input, index, starts[index])?
+                    }
+                    (ChunkRef::Base(entry), Some(base)) => base.read_entry(self, entry)?,
+                    (ChunkRef::Base(_), None) => return Err(CompressionError::InvalidFormat.into()),
+                };
+                append_range(&mut range, &data, block_start, offset, end);
             }
+            if block_end >= end {
+                break;
+            }
+        }
 
-            // Lire le bloc
-            let mut block = vec![0u8; block_size as usize];
-            if let Err(e) = input_file.read_exact(&mut block) {
-                return Err(CompressionError::IoError(io::Error::new(
-                    e.kind(),
-                    format!("Failed to read block at offset {}: {}", processed_bytes, e)
-                )).into());
+        Ok(range)
+    }
+
+    /// Lit un bloc à l'aide de l'index ; `start` : position d'origine de sa première occurrence
+    fn read_indexed_block<R: Read + Seek>(
+        &self,
+        parsed: &ParsedHeader,
+        key: &[u8],
+        footer: &ArchiveFooter,
+        input: &mut R,
+        index: usize,
+        start: u64,
+    ) -> Result<Vec<u8>> {
+        let entry = &footer.blocks[index];
+        input.seek(SeekFrom::Start(entry.offset))?;
+        let prefix = read_frame_prefix(input, &parsed.header, true)?;
+        let expected = FramePrefix::new(&parsed.header, entry.compressed_size, index as u32, start);
+        if prefix != Some(expected) || entry.compressed_size == 0 {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        let mut block = vec![0u8; entry.compressed_size as usize];
+        input.read_exact(&mut block)?;
+
+        let is_final = index + 1 == footer.blocks.len();
+        let data = self.open_block(parsed, key, index as u32, is_final, &block)
+            .map_err(|e| block_error(e, index as u32, entry.offset))?;
+        if data.len() as u64 != entry.size {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        if parsed.header.version >= VERSION_CHECKSUMS {
+            footer.verify_block(index, &blake3::hash(&data))?;
+        }
+        Ok(data)
+    }
+
+    /// Sérialise l'index des blocs, scellé avec la clé de l'archive si elle est chiffrée
+    fn seal_footer(&self, footer: &ArchiveFooter, header_bytes: &[u8], key: &[u8], nonce_prefix: &[u8]) -> Result<Vec<u8>> {
+        let footer_json = serde_json::to_vec(footer)?;
+        if key.is_empty() {
+            return Ok(footer_json);
+        }
+        let nonce = block_nonce(nonce_prefix, FOOTER_NONCE_INDEX);
+        let aad = block_aad(header_bytes, FOOTER_NONCE_INDEX, true);
+        self.encrypt_data(&footer_json, key, &nonce, &aad)
+    }
+
+    /// Lit l'index des blocs : avant la parité, à l'aide de son enregistrement (v14+), ou
+    /// à partir du trailer en fin d'archive
+    fn read_footer<R: Read + Seek>(&self, parsed: &ParsedHeader, key: &[u8], input: &mut R) -> Result<ArchiveFooter> {
+        if parsed.header.version < VERSION_PARITY_RECORD {
+            return self.read_trailing_footer(parsed, key, input);
+        }
+        let data_start = parsed.raw.len() as u64;
+        if let Some(parity) = read_parity_record(input, data_start, false)? {
+            return self.read_protected_footer(parsed, key, input, parity);
+        }
+        // Sans enregistrement avant le trailer : archive sans parité, ou fin d'archive
+        // abîmée dont la première copie de l'enregistrement est cherchée
+        match self.read_trailing_footer(parsed, key, input) {
+            Err(e) => match read_parity_record(input, data_start, true)? {
+                Some(parity) => self.read_protected_footer(parsed, key, input, parity),
+                None => Err(e),
+            },
+            footer => footer,
+        }
+    }
+
+    /// Lit l'index en fin de zone protégée, reconstruit au besoin par la parité
+    fn read_protected_footer<R: Read + Seek>(
+        &self,
+        parsed: &ParsedHeader,
+        key: &[u8],
+        input: &mut R,
+        parity: ParityInfo,
+    ) -> Result<ArchiveFooter> {
+        let mut footer = vec![0u8; parity.footer_len() as usize];
+        let mut reader = ParityReader::new(&mut *input, Some(parity.clone()), false);
+        reader.seek(SeekFrom::Start(parity.footer_start()))?;
+        reader.read_exact(&mut footer).map_err(from_io_error)?;
+        let mut footer = self.open_footer(parsed, key, footer)?;
+        footer.parity = Some(parity);
+        Ok(footer)
+    }
+
+    /// Lit l'index qui précède le trailer
+    fn read_trailing_footer<R: Read + Seek>(&self, parsed: &ParsedHeader, key: &[u8], input: &mut R) -> Result<ArchiveFooter> {
+        let file_size = input.seek(SeekFrom::End(0))?;
+        if file_size < parsed.raw.len() as u64 + frame_prefix_size(parsed.header.version) + TRAILER_SIZE {
+            return Err(self.missing_footer_error(parsed));
+        }
+
+        let mut trailer = [0u8; TRAILER_SIZE as usize];
+        input.seek(SeekFrom::End(-(TRAILER_SIZE as i64)))?;
+        input.read_exact(&mut trailer)?;
+        let footer_len = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        if &trailer[8..] != FOOTER_MAGIC || footer_len > file_size - parsed.raw.len() as u64 - TRAILER_SIZE {
+            return Err(self.missing_footer_error(parsed));
+        }
+
+        let mut footer = vec![0u8; footer_len as usize];
+        input.seek(SeekFrom::Start(file_size - TRAILER_SIZE - footer_len))?;
+        input.read_exact(&mut footer)?;
+        self.open_footer(parsed, key, footer)
+    }
+
+    /// Déchiffre (si besoin) et désérialise l'index des blocs
+    fn open_footer(&self, parsed: &ParsedHeader, key: &[u8], mut footer: Vec<u8>) -> Result<ArchiveFooter> {
+        if !key.is_empty() {
+            let nonce = block_nonce(&parsed.nonce, FOOTER_NONCE_INDEX);
+            let aad = block_aad(&parsed.raw, FOOTER_NONCE_INDEX, true);
+            footer = self.decrypt_data(&footer, key, &nonce, &aad)
+                .map_err(|_| CompressionError::AuthenticationError("block index was modified".into()))?;
+        }
+
+        let footer: ArchiveFooter = serde_json::from_slice(&footer)
+            .map_err(|_| CompressionError::InvalidFormat)?;
+        if footer.blocks.is_empty() {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        if let Some(parity) = &footer.parity {
+            if parsed.header.version >= VERSION_PARITY_RECORD {
+                return Err(CompressionError::InvalidFormat.into());
             }
+            parity.validate()?;
+        }
+        if footer.chunks.is_some() && parsed.header.version < VERSION_DEDUP {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        let refers_to_base = footer.chunks.iter().flatten().any(|&index| index as usize >= footer.blocks.len());
+        if refers_to_base && parsed.header.version < VERSION_INCREMENTAL {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        footer.validate_chunks()?;
+        Ok(footer)
+    }
+
+    fn missing_footer_error(&self, parsed: &ParsedHeader) -> anyhow::Error {
+        if parsed.header.encrypted {
+            CompressionError::AuthenticationError("archive is truncated".into()).into()
+        } else {
+            CompressionError::InvalidFormat.into()
+        }
+    }
+
+    /// Compresse un flux de taille connue : au plus `threads` blocs sont en cours
+    /// (lus, en compression ou en attente d'écriture) et chacun est écrit dans l'ordre
+    /// dès qu'il est prêt
+    fn compress_stream<R: Read, W: Write>(
+        &self,
+        reader: &mut R,
+        segments: &[u64],
+        header_bytes: &[u8],
+        key: &[u8],
+        nonce_prefix: &[u8],
+        frames: &mut FrameWriter<W>,
+    ) -> Result<()> {
+        if self.options.chunking == Chunking::ContentDefined || self.options.base_archive.is_some() {
+            return self.compress_chunked(reader, segments, header_bytes, key, nonce_prefix, frames);
+        }
+        let total_size: u64 = segments.iter().sum();
+        let block_size = self.options.block_size.max(1);
+        let block_count = (total_size.div_ceil(block_size as u64)).max(1);
+        if block_count >= FOOTER_NONCE_INDEX as u64 {
+            return Err(CompressionError::CompressionError("Too many blocks for this block size".into()).into());
+        }
+
+        let window = self.in_flight_blocks() as u64;
+        let start = Instant::now();
 
-            // Décompresser le bloc
-            let decompressed = match self.decompress_block(&block, &key, &nonce) {
-                Ok(data) => data,
-                Err(e) => {
-                    return Err(CompressionError::CompressionError(
-                        format!("Failed to decompress block at offset {}: {}", processed_bytes, e)
-                    ).into());
+        rayon::in_place_scope(|scope| {
+            let (sender, receiver) = mpsc::channel::<(u64, Vec<u8>, Result<Vec<u8>>)>();
+            // Blocs compressés arrivés avant leurs prédécesseurs
+            let mut ready = BTreeMap::new();
+            let mut remaining = total_size;
+            let mut written = 0u64;
+            let mut processed_bytes = 0u64;
+
+            for index in 0..=block_count {
+                // Fenêtre pleine (ou tout est lu) : écrire les blocs prêts dans l'ordre
+                while written < index && (index - written >= window || index == block_count) {
+                    while !ready.contains_key(&written) {
+                        let (done, chunk, result) = receiver.recv()
+                            .map_err(|_| CompressionError::CompressionError("Compression worker stopped".into()))?;
+                        ready.insert(done, (chunk, result));
+                    }
+                    let (chunk, result): (Vec<u8>, Result<Vec<u8>>) = ready.remove(&written).unwrap();
+                    frames.write_block(&result?, &chunk)?;
+                    written += 1;
+                    processed_bytes += chunk.len() as u64;
+                    self.report_progress(processed_bytes, total_size, start);
+                }
+                if index == block_count {
+                    break;
                 }
-            };
 
-            // Écrire le bloc décompressé
-            if let Err(e) = output_file.write_all(&decompressed) {
-                return Err(CompressionError::IoError(io::Error::new(
-                    e.kind(),
-                    format!("Failed to write decompressed block at offset {}: {}", processed_bytes, e)
-                )).into());
+                self.check_cancelled()?;
+                let len = remaining.min(block_size as u64) as usize;
+                let mut chunk = vec![0u8; len];
+                reader.read_exact(&mut chunk)?;
+                remaining -= len as u64;
+
+                let sender = sender.clone();
+                scope.spawn(move |_| {
+                    let nonce = block_nonce(nonce_prefix, index as u32);
+                    let aad = block_aad(header_bytes, index as u32, index + 1 == block_count);
+                    let result = self.compress_block(&chunk, key, &nonce, &aad);
+                    // Le récepteur a disparu si l'écriture a échoué : le résultat est abandonné
+                    let _ = sender.send((index, chunk, result));
+                });
             }
+            Ok(())
+        })
+    }
 
-            processed_bytes += block_size as u64 + 4;
-
-            // Mise à jour de la progression
-            if let Some(ref callback) = &self.progress_callback {
-                let elapsed = start.elapsed().as_secs_f64();
-                let speed = processed_bytes as f64 / elapsed;
-                let remaining = (file_size - HEADER_SIZE as u64 - processed_bytes) as f64 / speed;
-
-                if let Ok(mut guard) = callback.lock() {
-                    guard(ProgressInfo {
-                        processed_bytes,
-                        total_bytes: file_size - HEADER_SIZE as u64,
-                        current_speed: speed,
-                        estimated_remaining_time: remaining,
-                    });
-                }
+    fn report_progress(&self, processed_bytes: u64, total_bytes: u64, start: Instant) {
+        if let Some(ref callback) = &self.progress_callback {
+            let elapsed = start.elapsed().as_secs_f64();
+            let speed = processed_bytes as f64 / elapsed;
+            let remaining = total_bytes.saturating_sub(processed_bytes) as f64 / speed;
+
+            if let Ok(mut guard) = callback.lock() {
+                guard(ProgressInfo {
+                    processed_bytes,
+                    total_bytes,
+                    current_speed: speed,
+                    estimated_remaining_time: remaining,
+                });
             }
         }
-
-        output_file.flush()?;
-        Ok(())
     }
 
     pub fn get_metadata<P: AsRef<Path>>(&self, path: P) -> Result<FileMetadata> {
-        let path_ref = path.as_ref();
-        let mut file = BufReader::new(File::open(path_ref)?);
-        let (header, _, _) = self.read_header(&mut file)?;
-        let file_size = std::fs::metadata(path_ref)?.len();
-        
+        let mut file = BufReader::new(self.open_reader(path.as_ref())?);
+        let file_size = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(0))?;
+        let parsed = self.read_header(&mut file)?;
+
+        // L'empreinte est dans l'index, scellé si l'archive est chiffrée ; la taille d'une
+        // archive écrite en flux aussi
+        let mut checksum = String::new();
+        let mut original_size = parsed.header.original_size;
+        if parsed.header.version >= VERSION_CHECKSUMS && (!parsed.header.encrypted || self.options.password.is_some()) {
+            let key = self.archive_key(&parsed)?;
+            let footer = self.read_footer(&parsed, &key, &mut file)?;
+            checksum = footer.checksum.unwrap_or_default();
+            original_size = footer.original_size.unwrap_or(original_size);
+        }
+        let header = parsed.header;
+
         Ok(FileMetadata {
             original_name: header.original_name,
-            original_size: header.original_size,
+            original_size,
             compressed_size: file_size,
-            compression_ratio: header.original_size as f64 / file_size as f64,
+            compression_ratio: original_size as f64 / file_size as f64,
             encrypted: header.encrypted,
             creation_time: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)?
                 .as_secs(),
-            checksum: String::new(),
+            checksum,
             estimated_time: 0.0,
+            output_path: None,
         })
     }
 
-    fn write_header<W: Write>(&self, writer: &mut W, name: &str, size: u64, salt: &[u8], nonce: &[u8]) -> Result<()> {
-        let header = FileHeader {
+    /// Taille maximale d'une trame de l'archive : quatre blocs
+    pub(crate) fn max_frame_size(&self, header: &FileHeader) -> usize {
+        header.block_size.map_or(self.options.block_size, |size| size as usize).saturating_mul(4)
+    }
+
+    fn new_header(&self, name: &str, size: u64) -> Result<FileHeader> {
+        if self.options.block_size > MAX_BLOCK_SIZE {
+            return Err(CompressionError::CompressionError("block size cannot exceed 1 GiB".into()).into());
+        }
+        let dictionary = self.dictionary()?
+            .map(|dictionary| DictionaryInfo::new(dictionary, !self.options.external_dictionary));
+        let base = self.base_index()?.map(|index| index.info.clone());
+        Ok(FileHeader {
             magic: String::from_utf8_lossy(MAGIC_BYTES).into_owned(),
-            version: 1,
+            version: FORMAT_VERSION,
             encrypted: self.options.use_encryption,
             original_name: name.to_string(),
             original_size: size,
-        };
+            kdf: self.options.use_encryption.then_some(self.options.key_derivation),
+            multi_entry: false,
+            dictionary,
+            deduplicated: self.options.chunking == Chunking::ContentDefined || base.is_some(),
+            base,
+            block_size: Some(self.options.block_size.max(1) as u64),
+        })
+    }
 
-        let header_json = serde_json::to_string(&header)?;
+    fn encode_header(&self, header: &FileHeader, salt: &[u8], nonce_prefix: &[u8]) -> Result<Vec<u8>> {
+        let header_json = serde_json::to_string(header)?;
         let mut header_bytes = header_json.into_bytes();
         
         // S'assurer que l'en-tête ne dépasse pas la taille maximale
@@ -364,17 +1169,53 @@ impl Compressor {
         
         // Padding jusqu'à HEADER_SIZE
         header_bytes.resize(HEADER_SIZE, 0);
-        writer.write_all(&header_bytes)?;
 
         if self.options.use_encryption {
-            writer.write_all(salt)?;
-            writer.write_all(nonce)?;
+            header_bytes.extend_from_slice(salt);
+            header_bytes.extend_from_slice(nonce_prefix);
         }
 
-        Ok(())
+        // Le dictionnaire intégré est authentifié avec l'en-tête
+        if header.dictionary.as_ref().is_some_and(|info| info.embedded) {
+            header_bytes.extend_from_slice(self.dictionary()?.unwrap_or_default());
+        }
+
+        Ok(header_bytes)
+    }
+
+    /// Sortie d'une archive : fichier, jeu de volumes, ou image avec `use_steganography`
+    fn create_output(&self, path: &Path) -> Result<ArchiveOutput> {
+        if !self.options.use_steganography {
+            return ArchiveOutput::create(path, self.options.overwrite, self.options.volume_size);
+        }
+        let Some(image) = self.options.steganography_image.as_ref() else {
+            return Err(stego::stego_error(SteganographyError::Cover("steganography_image is not set".into())));
+        };
+        if self.options.volume_size > 0 {
+            return Err(stego::stego_error(SteganographyError::Cover(
+                "steganography cannot be combined with volumes".into()
+            )));
+        }
+        Ok(ArchiveOutput::Image(self.image_output(Path::new(image), path)?))
     }
 
-    fn read_header<R: Read>(&self, reader: &mut R) -> Result<(FileHeader, Vec<u8>, Vec<u8>)> {
+    /// Archive, jeu de volumes, ou image contenant une archive cachée
+    fn open_reader(&self, path: &Path) -> Result<ArchiveReader> {
+        match self.hidden_archive(path)? {
+            Some(data) => Ok(ArchiveReader::Memory(io::Cursor::new(data))),
+            None => ArchiveReader::open(path),
+        }
+    }
+
+    /// Ouvre une archive (ou un jeu de volumes) et lit son en-tête
+    fn open_archive(&self, path: &Path) -> Result<(BufReader<ArchiveReader>, ParsedHeader)> {
+        let mut input = BufReader::new(self.open_reader(path)?);
+        let mut parsed = self.read_header(&mut input)?;
+        parsed.location = Some(path.to_path_buf());
+        Ok((input, parsed))
+    }
+
+    fn read_header<R: Read>(&self, reader: &mut R) -> Result<ParsedHeader> {
         let mut header_bytes = vec![0u8; HEADER_SIZE];
         reader.read_exact(&mut header_bytes)?;
 
@@ -386,71 +1227,104 @@ impl Compressor {
         let header: FileHeader = serde_json::from_slice(&header_bytes[..json_end])
             .map_err(|_| CompressionError::InvalidFormat)?;
 
-        if header.magic.as_bytes() != MAGIC_BYTES {
+        if header.magic.as_bytes() != MAGIC_BYTES || header.version == 0 || header.version > FORMAT_VERSION {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        if header.base.is_some() && header.version < VERSION_INCREMENTAL {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        if header.block_size.is_some_and(|size| size == 0 || size > MAX_BLOCK_SIZE as u64) {
             return Err(CompressionError::InvalidFormat.into());
         }
 
         let mut salt = Vec::new();
         let mut nonce = Vec::new();
 
+        // v1 stocke un nonce complet, v2+ seulement le préfixe du fichier
         if header.encrypted {
             salt = vec![0u8; SALT_SIZE];
-            nonce = vec![0u8; NONCE_SIZE];
+            nonce = if header.version == LEGACY_VERSION {
+                vec![0u8; NONCE_SIZE]
+            } else {
+                vec![0u8; NONCE_PREFIX_SIZE]
+            };
             reader.read_exact(&mut salt)?;
             reader.read_exact(&mut nonce)?;
         }
 
-        Ok((header, salt, nonce))
+        let mut raw = header_bytes;
+        raw.extend_from_slice(&salt);
+        raw.extend_from_slice(&nonce);
+
+        let mut dictionary = None;
+        if let Some(info) = header.dictionary.as_ref().filter(|info| info.embedded) {
+            if info.size > MAX_DICTIONARY_SIZE as u64 {
+                return Err(CompressionError::InvalidFormat.into());
+            }
+            let mut bytes = vec![0u8; info.size as usize];
+            reader.read_exact(&mut bytes)?;
+            raw.extend_from_slice(&bytes);
+            dictionary = Some(bytes);
+        }
+
+        Ok(ParsedHeader { header, salt, nonce, dictionary, raw, location: None })
     }
 
-    fn encrypt_data(&self, data: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
-        // Version simplifiée avec un seul bloc
+    fn encrypt_data(&self, data: &[u8], key: &[u8], nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         let cipher = Aes256Gcm::new_from_slice(key)
             .map_err(|e| CompressionError::EncryptionError(e.to_string()))?;
         let nonce = Nonce::from_slice(nonce);
         
-        cipher.encrypt(nonce, data)
+        cipher.encrypt(nonce, Payload { msg: data, aad })
             .map_err(|e| CompressionError::EncryptionError(e.to_string()).into())
     }
 
-    fn decrypt_data(&self, data: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
-        // Version simplifiée avec un seul bloc
+    fn decrypt_data(&self, data: &[u8], key: &[u8], nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         let cipher = Aes256Gcm::new_from_slice(key)
             .map_err(|e| CompressionError::EncryptionError(e.to_string()))?;
         let nonce = Nonce::from_slice(nonce);
         
-        cipher.decrypt(nonce, data)
-            .map_err(|e| CompressionError::EncryptionError(format!("Decryption failed: {}", e)).into())
+        // Un échec de vérification du tag signifie une altération (ou un mauvais mot de passe)
+        cipher.decrypt(nonce, Payload { msg: data, aad })
+            .map_err(|_| CompressionError::AuthenticationError("invalid authentication tag".into()).into())
     }
 
-    fn compress_block(&self, data: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
-        // Compression avec zstd
-        let mut compressed = Vec::with_capacity(data.len());
-        {
-            let mut encoder = zstd::Encoder::new(&mut compressed, self.options.level as i32)?;
-            encoder.write_all(data)?;
-            encoder.finish()?;
-        }
+    fn compress_block(&self, data: &[u8], key: &[u8], nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        // Compression, précédée de l'identifiant du codec
+        let compressed = self.options.codec.encode_tagged(
+            data, self.options.level, self.options.codec_policy, self.dictionary()?
+        )?;
 
         // Chiffrement si nécessaire
         if !key.is_empty() {
-            self.encrypt_data(&compressed, key, nonce)
+            self.encrypt_data(&compressed, key, nonce, aad)
         } else {
             Ok(compressed)
         }
     }
 
-    fn decompress_block(&self, data: &[u8], key: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    /// `tagged` : le bloc commence par l'identifiant du codec (sinon zstd, versions < 7)
+    fn decompress_block(
+        &self,
+        data: &[u8],
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        tagged: bool,
+        dictionary: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
         let to_decompress = if !key.is_empty() {
-            self.decrypt_data(data, key, nonce)?
+            self.decrypt_data(data, key, nonce, aad)?
         } else {
             data.to_vec()
         };
 
-        let mut decoder = zstd::Decoder::new(&to_decompress[..])?;
-        let mut decoded = Vec::with_capacity(self.options.block_size);
-        decoder.read_to_end(&mut decoded)?;
-        Ok(decoded)
+        let (codec, payload) = match to_decompress.split_first() {
+            Some((&id, payload)) if tagged => (Codec::from_id(id)?, payload),
+            None if tagged => return Err(CompressionError::InvalidFormat.into()),
+            _ => (Codec::Zstd, &to_decompress[..]),
+        };
+        codec.decode(payload, self.options.block_size, dictionary)
     }
 
     fn prepare_encryption(&self) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>)> {
@@ -460,124 +1334,146 @@ impl Compressor {
         let mut salt = vec![0u8; SALT_SIZE];
         getrandom::getrandom(&mut salt)?;
 
-        let mut nonce = vec![0u8; NONCE_SIZE];
-        getrandom::getrandom(&mut nonce)?;
+        let mut nonce_prefix = vec![0u8; NONCE_PREFIX_SIZE];
+        getrandom::getrandom(&mut nonce_prefix)?;
 
-        let key = self.derive_key(password, &salt)?;
+        let key = self.options.key_derivation.derive_key(password, &salt)?;
 
-        Ok((key, salt, nonce))
+        Ok((key, salt, nonce_prefix))
     }
+}
 
-    fn derive_key(&self, password: &str, salt: &[u8]) -> Result<Vec<u8>> {
-        let mut key = vec![0u8; KEY_SIZE];
-        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(
-            password.as_bytes(),
-            salt,
-            10_000, // Réduit pour plus de rapidité
-            &mut key,
-        );
-        Ok(key)
+/// Nonce propre à chaque bloc : préfixe du fichier (8 octets) suivi de l'index du bloc (4 octets)
+fn block_nonce(prefix: &[u8], index: u32) -> Vec<u8> {
+    if prefix.is_empty() {
+        return Vec::new();
     }
+    let mut nonce = Vec::with_capacity(NONCE_SIZE);
+    nonce.extend_from_slice(prefix);
+    nonce.extend_from_slice(&index.to_be_bytes());
+    nonce
+}
 
-    pub fn hide_in_image<P: AsRef<Path>>(&self, archive_path: P, image_path: P, output_path: P) -> Result<()> {
-        let archive_data = std::fs::read(archive_path)?;
-        let img = image::open(image_path)
-            .map_err(|e| CompressionError::SteganographyError(e.to_string()))?;
-        
-        let img_rgba = img.to_rgba8();
-        let (width, height) = img_rgba.dimensions();
-        let max_bytes = (width * height * 3) / 8; // 3 bits par pixel (1 par canal RGB)
-        
-        if archive_data.len() as u32 > max_bytes {
-            return Err(CompressionError::SteganographyError(
-                format!("Archive too large for this image. Max size: {} bytes", max_bytes)
-            ).into());
-        }
-        
-        let mut stego_img = RgbaImage::new(width, height);
-        let mut bit_index = 0;
-        
-        // Écrire la taille de l'archive sur les 32 premiers pixels
-        let size_bytes = (archive_data.len() as u32).to_le_bytes();
-        for (i, &byte) in size_bytes.iter().enumerate() {
-            for bit in 0..8 {
-                let x = (i * 8 + bit) as u32 % width;
-                let y = (i * 8 + bit) as u32 / width;
-                let mut pixel = img_rgba.get_pixel(x, y).clone();
-                pixel[0] &= 0xFE;
-                pixel[0] |= ((byte >> bit) & 1) as u8;
-                stego_img.put_pixel(x, y, pixel);
-            }
-        }
-        
-        // Écrire les données de l'archive
-        for &byte in archive_data.iter() {
-            for bit in 0..8 {
-                let pixel_index = 32 + bit_index; // 32 pixels après la taille
-                let x = pixel_index as u32 % width;
-                let y = pixel_index as u32 / width;
-                let mut pixel = img_rgba.get_pixel(x, y).clone();
-                
-                // Modifier le LSB du canal rouge
-                pixel[0] &= 0xFE;
-                pixel[0] |= ((byte >> bit) & 1) as u8;
-                
-                stego_img.put_pixel(x, y, pixel);
-                bit_index += 1;
-            }
-        }
-        
-        // Copier le reste de l'image
-        for y in 0..height {
-            for x in 0..width {
-                if (y * width + x) as usize >= 32 + bit_index {
-                    stego_img.put_pixel(x, y, *img_rgba.get_pixel(x, y));
-                }
-            }
-        }
-        
-        stego_img.save(output_path)
-            .map_err(|e| CompressionError::SteganographyError(e.to_string()).into())
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    Ok(path.file_name()
+        .ok_or_else(|| CompressionError::IoError(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Invalid input filename"
+        )))?
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Données associées d'un bloc : en-tête brut, index du bloc et marqueur de dernier bloc
+fn block_aad(header: &[u8], index: u32, is_final: bool) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(header.len() + 5);
+    aad.extend_from_slice(header);
+    aad.extend_from_slice(&index.to_be_bytes());
+    aad.push(is_final as u8);
+    aad
+}
+
+/// Préfixe d'une trame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FramePrefix {
+    size: u32,
+    /// Index de la trame et position de son contenu dans les données d'origine (v11+)
+    sync: Option<(u32, u64)>,
+}
+
+impl FramePrefix {
+    fn new(header: &FileHeader, size: u32, index: u32, position: u64) -> Self {
+        let sync = (header.version >= VERSION_SYNC).then_some((index, position));
+        Self { size, sync }
     }
 
-    pub fn extract_from_image<P: AsRef<Path>>(&self, image_path: P, output_path: P) -> Result<()> {
-        let img = image::open(image_path)
-            .map_err(|e| CompressionError::SteganographyError(e.to_string()))?;
-        let img_rgba = img.to_rgba8();
-        let (width, height) = img_rgba.dimensions();
-        
-        // Lire la taille de l'archive
-        let mut size_bytes = [0u8; 4];
-        for (i, byte) in size_bytes.iter_mut().enumerate() {
-            for bit in 0..8 {
-                let x = (i * 8 + bit) as u32 % width;
-                let y = (i * 8 + bit) as u32 / width;
-                let pixel = img_rgba.get_pixel(x, y);
-                *byte |= ((pixel[0] & 1) as u8) << bit;
-            }
-        }
-        let archive_size = u32::from_le_bytes(size_bytes) as usize;
-        
-        // Vérifier que la taille est valide
-        let max_bytes = (width * height * 3) / 8;
-        if archive_size as u32 > max_bytes {
-            return Err(CompressionError::SteganographyError("Invalid archive size in image".into()).into());
+    /// Décode un préfixe de `frame_prefix_size(version)` octets ; `None` si le marqueur est absent
+    fn parse(bytes: &[u8], version: u32) -> Option<Self> {
+        if version < VERSION_SYNC {
+            return Some(Self { size: u32::from_le_bytes(bytes[..4].try_into().unwrap()), sync: None });
         }
-        
-        // Extraire les données de l'archive
-        let mut archive_data = vec![0u8; archive_size];
-        for (byte_idx, byte) in archive_data.iter_mut().enumerate() {
-            for bit in 0..8 {
-                let pixel_index = 32 + (byte_idx * 8 + bit); // 32 pixels après la taille
-                let x = pixel_index as u32 % width;
-                let y = pixel_index as u32 / width;
-                let pixel = img_rgba.get_pixel(x, y);
-                *byte |= ((pixel[0] & 1) as u8) << bit;
-            }
+        if &bytes[..SYNC_MARKER.len()] != SYNC_MARKER {
+            return None;
         }
-        
-        std::fs::write(output_path, archive_data)?;
-        Ok(())
+        let index = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        let position = u64::from_le_bytes(bytes[12..20].try_into().unwrap());
+        let size = u32::from_le_bytes(bytes[20..24].try_into().unwrap());
+        Some(Self { size, sync: Some((index, position)) })
+    }
+}
+
+/// Taille du préfixe des trames : longueur (u32 LE), précédée de l'en-tête de synchronisation (v11+)
+fn frame_prefix_size(version: u32) -> u64 {
+    if version >= VERSION_SYNC { SYNC_HEADER_SIZE as u64 + 4 } else { 4 }
+}
+
+/// Lit le préfixe d'une trame ; `None` sur une fin de fichier propre (non stricte)
+fn read_frame_prefix<R: Read>(input: &mut R, header: &FileHeader, strict: bool) -> Result<Option<FramePrefix>> {
+    let authenticated = header.encrypted && header.version >= VERSION_AUTHENTICATED;
+    let mut prefix = vec![0u8; frame_prefix_size(header.version) as usize];
+    match input.read_exact(&mut prefix) {
+        Ok(()) => FramePrefix::parse(&prefix, header.version).map(Some).ok_or_else(|| CompressionError::InvalidFormat.into()),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && !strict => Ok(None),
+        Err(e) => Err(truncation_error(e, authenticated)),
+    }
+}
+
+/// Contextualise l'erreur d'un bloc ; les échecs d'authentification gardent leur type
+fn block_error(e: anyhow::Error, index: u32, offset: u64) -> anyhow::Error {
+    if let Some(CompressionError::AuthenticationError(_)) = e.downcast_ref::<CompressionError>() {
+        return CompressionError::AuthenticationError(format!(
+            "block {} was modified, reordered or sealed with another password", index
+        )).into();
+    }
+    CompressionError::CompressionError(
+        format!("Failed to decompress block at offset {}: {}", offset, e)
+    ).into()
+}
+
+/// Ajoute à `range` la partie de `data` (débutant à `block_start`) comprise dans [start, end)
+fn append_range(range: &mut Vec<u8>, data: &[u8], block_start: u64, start: u64, end: u64) {
+    let block_end = block_start + data.len() as u64;
+    if block_end <= start || block_start >= end {
+        return;
+    }
+    let from = start.saturating_sub(block_start) as usize;
+    let to = (end.min(block_end) - block_start) as usize;
+    range.extend_from_slice(&data[from..to]);
+}
+
+/// Une fin de fichier prématurée dans une archive authentifiée est une troncature
+fn truncation_error(e: io::Error, authenticated: bool) -> anyhow::Error {
+    if authenticated && e.kind() == io::ErrorKind::UnexpectedEof {
+        CompressionError::AuthenticationError("archive is truncated".into()).into()
+    } else {
+        from_io_error(e)
+    }
+}
+
+/// Convertit une erreur du format en `io::Error`, en conservant `CompressionError`
+fn into_io_error(e: anyhow::Error) -> io::Error {
+    match e.downcast::<CompressionError>() {
+        Ok(CompressionError::IoError(e)) => e,
+        Ok(e) => io::Error::new(io::ErrorKind::InvalidData, e),
+        Err(e) => match e.downcast::<io::Error>() {
+            Ok(e) => e,
+            Err(e) => io::Error::other(e),
+        },
+    }
+}
+
+/// Inverse de `into_io_error` : retrouve la `CompressionError` transportée par un lecteur
+fn from_io_error(e: io::Error) -> anyhow::Error {
+    if !e.get_ref().is_some_and(|inner| inner.is::<CompressionError>()) {
+        return e.into();
+    }
+    match e.into_inner().map(|inner| inner.downcast::<CompressionError>()) {
+        Some(Ok(inner)) => (*inner).into(),
+        _ => unreachable!("type vérifié ci-dessus"),
     }
 }
 
@@ -650,4 +1546,550 @@ mod tests {
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_encrypted_multi_block_unique_nonces() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("test.bin");
+        let compressed_path = dir.path().join("test.ntk");
+        let output_path = dir.path().join("test_out.bin");
+
+        // Plusieurs blocs identiques : avec un nonce unique, les chiffrés doivent différer
+        let test_data = vec![0x42u8; 4 * 1024];
+        fs::write(&input_path, &test_data)?;
+
+        let options = CompressionOptions {
+            block_size: 1024,
+            use_encryption: true,
+            password: Some("test123".to_string()),
+            ..Default::default()
+        };
+        let compressor = Compressor::new(options);
+        compressor.compress(&input_path, &compressed_path)?;
+
+        let archive = fs::read(&compressed_path)?;
+        let mut offset = HEADER_SIZE + SALT_SIZE + NONCE_PREFIX_SIZE;
+        let mut blocks = Vec::new();
+        loop {
+            let data = offset + SYNC_HEADER_SIZE + 4;
+            let len = u32::from_le_bytes(archive[data - 4..data].try_into().unwrap()) as usize;
+            if len == 0 {
+                break;
+            }
+            blocks.push(archive[data..data + len].to_vec());
+            offset = data + len;
+        }
+        assert_eq!(blocks.len(), 4);
+        assert_ne!(blocks[0], blocks[1]);
+
+        compressor.decompress(&compressed_path, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, test_data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decompress_legacy_v1_encrypted() -> Result<()> {
+        let dir = tempdir()?;
+        let compressed_path = dir.path().join("legacy.ntk");
+        let output_path = dir.path().join("legacy_out.txt");
+
+        let options = CompressionOptions {
+            block_size: 1024,
+            use_encryption: true,
+            password: Some("test123".to_string()),
+            ..Default::default()
+        };
+        let compressor = Compressor::new(options);
+
+        // Reconstituer une archive v1 : nonce unique partagé par tous les blocs
+        let test_data = b"Legacy archive".repeat(200);
+        let salt = [7u8; SALT_SIZE];
+        let nonce = [9u8; NONCE_SIZE];
+        let key = KeyDerivation::LEGACY.derive_key("test123", &salt)?;
+
+        let header = FileHeader {
+            magic: String::from_utf8_lossy(MAGIC_BYTES).into_owned(),
+            version: LEGACY_VERSION,
+            encrypted: true,
+            original_name: "legacy.txt".into(),
+            original_size: test_data.len() as u64,
+            kdf: None,
+            multi_entry: false,
+            dictionary: None,
+            deduplicated: false,
+            base: None,
+            block_size: None,
+        };
+        let mut archive = serde_json::to_vec(&header)?;
+        archive.resize(HEADER_SIZE, 0);
+        archive.extend_from_slice(&salt);
+        archive.extend_from_slice(&nonce);
+        for chunk in test_data.chunks(1024) {
+            let block = compressor.encrypt_data(&zstd::encode_all(chunk, 3)?, &key, &nonce, &[])?;
+            archive.extend_from_slice(&(block.len() as u32).to_le_bytes());
+            archive.extend_from_slice(&block);
+        }
+        fs::write(&compressed_path, &archive)?;
+
+        compressor.decompress(&compressed_path, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, test_data);
+
+        Ok(())
+    }
+
+    /// Archive découpée en (compresseur, en-tête, trames, fin)
+    type ArchiveParts = (Compressor, Vec<u8>, Vec<Vec<u8>>, Vec<u8>);
+
+    /// Compresse 4 blocs chiffrés et renvoie l'archive découpée
+    fn encrypted_archive_frames(dir: &Path) -> Result<ArchiveParts> {
+        let input_path = dir.join("frames.bin");
+        let compressed_path = dir.join("frames.ntk");
+        fs::write(&input_path, (0..4096u32).map(|i| (i % 251) as u8).collect::<Vec<_>>())?;
+
+        let compressor = Compressor::new(CompressionOptions {
+            block_size: 1024,
+            use_encryption: true,
+            password: Some("test123".to_string()),
+            ..Default::default()
+        });
+        compressor.compress(&input_path, &compressed_path)?;
+
+        let archive = fs::read(&compressed_path)?;
+        let data_start = HEADER_SIZE + SALT_SIZE + NONCE_PREFIX_SIZE;
+        let mut offset = data_start;
+        let mut frames = Vec::new();
+        loop {
+            let data = offset + SYNC_HEADER_SIZE + 4;
+            let len = u32::from_le_bytes(archive[data - 4..data].try_into().unwrap()) as usize;
+            if len == 0 {
+                break;
+            }
+            frames.push(archive[offset..data + len].to_vec());
+            offset = data + len;
+        }
+        Ok((compressor, archive[..data_start].to_vec(), frames, archive[offset..].to_vec()))
+    }
+
+    fn assert_authentication_error(compressor: &Compressor, dir: &Path, archive: &[u8]) {
+        let tampered_path = dir.join("tampered.ntk");
+        fs::write(&tampered_path, archive).unwrap();
+        let err = compressor.decompress(&tampered_path, &dir.join("tampered.out")).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CompressionError>(),
+            Some(CompressionError::AuthenticationError(_))
+        ), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_tampered_header_is_rejected() -> Result<()> {
+        let dir = tempdir()?;
+        let (compressor, mut header, frames, tail) = encrypted_archive_frames(dir.path())?;
+
+        // Renommer le fichier d'origine dans l'en-tête JSON
+        let pos = header.windows(6).position(|w| w == b"frames").unwrap();
+        header[pos] = b'g';
+        assert_authentication_error(&compressor, dir.path(), &[header, frames.concat(), tail].concat());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reordered_blocks_are_rejected() -> Result<()> {
+        let dir = tempdir()?;
+        let (compressor, header, mut frames, tail) = encrypted_archive_frames(dir.path())?;
+
+        frames.swap(0, 1);
+        assert_authentication_error(&compressor, dir.path(), &[header, frames.concat(), tail].concat());
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_archive_is_rejected() -> Result<()> {
+        let dir = tempdir()?;
+        let (compressor, header, mut frames, tail) = encrypted_archive_frames(dir.path())?;
+
+        // Supprimer le dernier bloc complet, avec ou sans l'index qui le suit
+        frames.pop();
+        assert_authentication_error(&compressor, dir.path(), &[header.clone(), frames.concat(), tail].concat());
+        assert_authentication_error(&compressor, dir.path(), &[header.clone(), frames.concat()].concat());
+
+        // Couper au milieu d'un bloc
+        let mut partial = [header, frames.concat()].concat();
+        partial.truncate(partial.len() - 10);
+        assert_authentication_error(&compressor, dir.path(), &partial);
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_encrypted_file() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("empty.txt");
+        let compressed_path = dir.path().join("empty.ntk");
+        let output_path = dir.path().join("empty_out.txt");
+        fs::write(&input_path, b"")?;
+
+        let compressor = Compressor::new(CompressionOptions {
+            use_encryption: true,
+            password: Some("test123".to_string()),
+            ..Default::default()
+        });
+        compressor.compress(&input_path, &compressed_path)?;
+
+        // Même vide, l'archive contient un bloc final : la retirer doit être détecté
+        compressor.decompress(&compressed_path, &output_path)?;
+        assert!(fs::read(&output_path)?.is_empty());
+
+        let archive = fs::read(&compressed_path)?;
+        assert_authentication_error(&compressor, dir.path(), &archive[..HEADER_SIZE + SALT_SIZE + NONCE_PREFIX_SIZE]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_derivation_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("kdf.txt");
+        let test_data = b"Key derivation".repeat(100);
+        fs::write(&input_path, &test_data)?;
+
+        let kdfs = [
+            KeyDerivation::Argon2id { memory_kib: 1024, iterations: 1, parallelism: 1 },
+            KeyDerivation::Scrypt { log_n: 10, r: 8, p: 1 },
+            KeyDerivation::Pbkdf2 { iterations: 1_000 },
+        ];
+        for kdf in kdfs {
+            let compressed_path = dir.path().join("kdf.ntk");
+            let output_path = dir.path().join("kdf_out.txt");
+            let compressor = Compressor::new(CompressionOptions {
+                use_encryption: true,
+                password: Some("test123".to_string()),
+                key_derivation: kdf,
+                ..Default::default()
+            });
+            compressor.compress(&input_path, &compressed_path)?;
+
+            // Les paramètres sont relus depuis l'en-tête, pas depuis les options
+            let mut file = File::open(&compressed_path)?;
+            assert_eq!(compressor.read_header(&mut file)?.header.kdf, Some(kdf));
+
+            let decompressor = Compressor::new(CompressionOptions {
+                password: Some("test123".to_string()),
+                ..Default::default()
+            });
+            decompressor.decompress(&compressed_path, &output_path)?;
+            assert_eq!(fs::read(&output_path)?, test_data);
+        }
+
+        // Coûts hors limites, comme ceux d'un en-tête forgé
+        let costly = [
+            KeyDerivation::Argon2id { memory_kib: 1024, iterations: 65, parallelism: 1 },
+            KeyDerivation::Argon2id { memory_kib: 1024, iterations: 1, parallelism: 65 },
+            KeyDerivation::Scrypt { log_n: 10, r: 8, p: 1 << 20 },
+            KeyDerivation::Pbkdf2 { iterations: u32::MAX },
+        ];
+        for kdf in costly {
+            let err = kdf.derive_key("test123", &[0u8; SALT_SIZE]).unwrap_err();
+            assert!(matches!(err.downcast_ref::<CompressionError>(), Some(CompressionError::InvalidFormat)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_range() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("range.bin");
+        let test_data: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+        fs::write(&input_path, &test_data)?;
+
+        for use_encryption in [false, true] {
+            let compressed_path = dir.path().join("range.ntk");
+            let compressor = Compressor::new(CompressionOptions {
+                block_size: 1024,
+                use_encryption,
+                password: Some("test123".to_string()),
+                ..Default::default()
+            });
+            compressor.compress(&input_path, &compressed_path)?;
+
+            // Plage à cheval sur plusieurs blocs, dans un bloc, puis au-delà de la fin
+            assert_eq!(compressor.read_range(&compressed_path, 1000, 3000)?, &test_data[1000..4000]);
+            assert_eq!(compressor.read_range(&compressed_path, 2048, 10)?, &test_data[2048..2058]);
+            assert_eq!(compressor.read_range(&compressed_path, 9990, 100)?, &test_data[9990..]);
+            assert!(compressor.read_range(&compressed_path, 20_000, 10)?.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_range_tampered_index_is_rejected() -> Result<()> {
+        let dir = tempdir()?;
+        let (compressor, header, frames, mut tail) = encrypted_archive_frames(dir.path())?;
+
+        // Modifier un octet de l'index chiffré
+        tail[SYNC_HEADER_SIZE + 4 + 2] ^= 1;
+        let tampered_path = dir.path().join("tampered.ntk");
+        fs::write(&tampered_path, [header, frames.concat(), tail].concat())?;
+        let err = compressor.read_range(&tampered_path, 0, 10).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CompressionError>(),
+            Some(CompressionError::AuthenticationError(_))
+        ), "unexpected error: {}", err);
+        Ok(())
+    }
+
+    #[test]
+    fn test_codecs_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("codec.txt");
+        let test_data = b"Codec roundtrip, codec roundtrip. ".repeat(300);
+        fs::write(&input_path, &test_data)?;
+
+        for codec in [Codec::Zstd, Codec::Lz4, Codec::Brotli, Codec::Stored, Codec::Auto] {
+            let compressed_path = dir.path().join("codec.ntk");
+            let output_path = dir.path().join("codec_out.txt");
+            let compressor = Compressor::new(CompressionOptions {
+                block_size: 4096,
+                codec,
+                level: 5,
+                ..Default::default()
+            });
+            compressor.compress(&input_path, &compressed_path)?;
+
+            // Le codec est enregistré dans chaque bloc : les options de lecture n'importent pas
+            Compressor::new(CompressionOptions::default()).decompress(&compressed_path, &output_path)?;
+            assert_eq!(fs::read(&output_path)?, test_data, "{:?}", codec);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_blocks_read_with_other_options() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("large.bin");
+        let compressed_path = dir.path().join("large.ntk");
+        let output_path = dir.path().join("large.out");
+        let mut test_data = vec![0u8; 600 * 1024];
+        getrandom::getrandom(&mut test_data)?;
+        fs::write(&input_path, &test_data)?;
+
+        // Trames de 256 Kio, au-delà de la limite qu'imposeraient les blocs du lecteur
+        Compressor::new(CompressionOptions { block_size: 256 * 1024, codec: Codec::Stored, ..Default::default() })
+            .compress(&input_path, &compressed_path)?;
+        let reader = Compressor::new(CompressionOptions { block_size: 16 * 1024, ..Default::default() });
+        reader.decompress(&compressed_path, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, test_data);
+        reader.verify(&compressed_path)?;
+
+        let oversized = CompressionOptions { block_size: MAX_BLOCK_SIZE + 1, ..Default::default() };
+        assert!(Compressor::new(oversized).compress(&input_path, &compressed_path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_checksums_are_verified() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("checksum.bin");
+        let compressed_path = dir.path().join("checksum.ntk");
+        let test_data: Vec<u8> = (0..3000u32).map(|i| (i % 241) as u8).collect();
+        fs::write(&input_path, &test_data)?;
+
+        // Blocs stockés sans compression ni chiffrement : une altération passe le décodage
+        let compressor = Compressor::new(CompressionOptions {
+            block_size: 1024,
+            codec: Codec::Stored,
+            ..Default::default()
+        });
+        let metadata = compressor.compress(&input_path, &compressed_path)?;
+        assert_eq!(metadata.checksum, blake3::hash(&test_data).to_hex().as_str());
+        assert_eq!(compressor.get_metadata(&compressed_path)?.checksum, metadata.checksum);
+        compressor.verify(&compressed_path)?;
+
+        let mut archive = fs::read(&compressed_path)?;
+        archive[HEADER_SIZE + SYNC_HEADER_SIZE + 4 + 1 + 10] ^= 1;
+        fs::write(&compressed_path, &archive)?;
+
+        let assert_integrity_error = |result: Result<()>| {
+            let err = result.unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<CompressionError>(),
+                Some(CompressionError::IntegrityError(_))
+            ), "unexpected error: {}", err);
+        };
+        assert_integrity_error(compressor.verify(&compressed_path));
+        assert_integrity_error(compressor.decompress(&compressed_path, &dir.path().join("checksum_out.bin")).map(|_| ()));
+        assert_integrity_error(compressor.read_range(&compressed_path, 0, 10).map(|_| ()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_preserves_block_order() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("pipeline.bin");
+        let compressed_path = dir.path().join("pipeline.ntk");
+        let output_path = dir.path().join("pipeline_out.bin");
+
+        // Blocs alternativement aléatoires et répétitifs : ils ne terminent pas dans l'ordre
+        let mut test_data = Vec::new();
+        for i in 0..64u8 {
+            let mut block = vec![i; 1024];
+            if i % 2 == 0 {
+                getrandom::getrandom(&mut block)?;
+            }
+            test_data.extend_from_slice(&block);
+        }
+        test_data.extend_from_slice(b"tail");
+        fs::write(&input_path, &test_data)?;
+
+        let compressor = Compressor::new(CompressionOptions {
+            block_size: 1024,
+            threads: 3,
+            level: 1,
+            ..Default::default()
+        });
+        let metadata = compressor.compress(&input_path, &compressed_path)?;
+        assert_eq!(metadata.checksum, blake3::hash(&test_data).to_hex().as_str());
+
+        compressor.decompress(&compressed_path, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, test_data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_decode_window() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("window.bin");
+        let compressed_path = dir.path().join("window.ntk");
+        let output_path = dir.path().join("window_out.bin");
+
+        let mut test_data = vec![0u8; 40 * 1024 + 17];
+        getrandom::getrandom(&mut test_data[..20 * 1024])?;
+        fs::write(&input_path, &test_data)?;
+
+        let options = CompressionOptions {
+            block_size: 1024,
+            threads: 4,
+            use_encryption: true,
+            password: Some("window".into()),
+            key_derivation: KeyDerivation::Pbkdf2 { iterations: 1000 },
+            ..Default::default()
+        };
+        Compressor::new(options.clone()).compress(&input_path, &compressed_path)?;
+
+        // Le résultat ne dépend pas du nombre de blocs en vol
+        for in_flight_blocks in [1, 3, 64] {
+            let compressor = Compressor::new(CompressionOptions { in_flight_blocks, ..options.clone() });
+            compressor.decompress(&compressed_path, &output_path)?;
+            assert_eq!(fs::read(&output_path)?, test_data);
+        }
+
+        // Un bloc altéré au milieu de la fenêtre est toujours signalé
+        let mut archive = fs::read(&compressed_path)?;
+        let middle = archive.len() / 2;
+        archive[middle] ^= 0x01;
+        fs::write(&compressed_path, &archive)?;
+        let compressor = Compressor::new(CompressionOptions { in_flight_blocks: 8, ..options });
+        assert!(compressor.decompress(&compressed_path, &output_path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancellation_removes_output() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("cancel.txt");
+        let compressed_path = dir.path().join("cancel.ntk");
+        let output_path = dir.path().join("cancel_out.txt");
+        fs::write(&input_path, b"Cancel me. ".repeat(4096))?;
+
+        let options = CompressionOptions { block_size: 1024, threads: 2, ..Default::default() };
+        Compressor::new(options.clone()).compress(&input_path, &compressed_path)?;
+
+        // Annulation pendant l'opération, depuis le callback de progression
+        let token = CancellationToken::new();
+        let mut compressor = Compressor::new(options);
+        compressor.set_cancellation_token(token.clone());
+        let cancel = token.clone();
+        compressor.set_progress_callback(move |_| cancel.cancel());
+
+        let error = compressor.decompress(&compressed_path, &output_path).unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(CompressionError::Cancelled)));
+        assert!(!output_path.exists());
+
+        let cancelled_path = dir.path().join("cancelled.ntk");
+        let error = compressor.compress(&input_path, &cancelled_path).unwrap_err();
+        assert!(matches!(error.downcast_ref(), Some(CompressionError::Cancelled)));
+        assert!(!cancelled_path.exists());
+        // Aucun fichier temporaire ne reste à côté des sorties
+        assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parity_repairs_archive() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("parity.bin");
+        let compressed_path = dir.path().join("parity.ntk");
+        let output_path = dir.path().join("parity_out.bin");
+
+        let mut test_data = vec![0u8; 300 * 1024];
+        getrandom::getrandom(&mut test_data)?;
+        fs::write(&input_path, &test_data)?;
+
+        let options = CompressionOptions {
+            block_size: 64 * 1024,
+            parity_percent: 40,
+            use_encryption: true,
+            password: Some("parity".into()),
+            key_derivation: KeyDerivation::Pbkdf2 { iterations: 1000 },
+            ..Default::default()
+        };
+        let compressor = Compressor::new(options);
+        compressor.compress(&input_path, &compressed_path)?;
+        assert!(compressor.repair(&compressed_path)?.is_empty());
+
+        // Quelques octets altérés dans deux blocs
+        let mut archive = fs::read(&compressed_path)?;
+        for offset in [1000, 150 * 1024] {
+            archive[offset] ^= 0xFF;
+        }
+        fs::write(&compressed_path, &archive)?;
+
+        // Reconstruction transparente à la lecture, sans toucher au fichier
+        compressor.decompress(&compressed_path, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, test_data);
+        assert_eq!(fs::read(&compressed_path)?, archive);
+
+        let report = compressor.repair(&compressed_path)?;
+        // Un fragment peut recouvrir deux blocs voisins
+        assert_eq!(report.repaired_blocks.first(), Some(&0));
+        assert!(report.repaired_blocks.contains(&2));
+        assert_eq!(report.repaired_parity_shards, 0);
+        compressor.verify(&compressed_path)?;
+        assert!(compressor.repair(&compressed_path)?.is_empty());
+
+        // L'archive se relit aussi en flux, index placé avant la parité
+        let mut streamed = Vec::new();
+        stream::NtkReader::new(compressor.clone(), fs::File::open(&compressed_path)?)?.read_to_end(&mut streamed)?;
+        assert_eq!(streamed, test_data);
+
+        // Index scellé, copie de l'enregistrement de parité qui précède le trailer et un
+        // bloc abîmés : la première copie de l'enregistrement suffit
+        let archive = fs::read(&compressed_path)?;
+        let end = archive.len() as u64 - TRAILER_SIZE;
+        let parity = parity::record_ending_at(&mut io::Cursor::new(&archive), end)?.unwrap();
+        let mut damaged = archive.clone();
+        damaged[parity.footer_start() as usize + 5] ^= 0xFF;
+        damaged[end as usize - 45] ^= 0xFF;
+        damaged[2000] ^= 0xFF;
+        fs::write(&compressed_path, &damaged)?;
+
+        compressor.decompress(&compressed_path, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, test_data);
+        // Le fragment de l'index recouvre aussi la fin des derniers blocs
+        let report = compressor.repair(&compressed_path)?;
+        assert_eq!(report.repaired_blocks.first(), Some(&0));
+        compressor.verify(&compressed_path)?;
+        // La copie de l'enregistrement, hors zone protégée, n'est pas réécrite
+        let repaired = fs::read(&compressed_path)?;
+        assert_eq!(repaired[..end as usize - 45], archive[..end as usize - 45]);
+        Ok(())
+    }
+}
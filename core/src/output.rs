@@ -0,0 +1,429 @@
+//! Écriture atomique des fichiers de sortie
+//!
+//! Chaque sortie est écrite dans un fichier temporaire voisin, synchronisé sur disque
+//! puis renommé à sa place une fois complet : une erreur, une annulation ou un arrêt
+//! brutal ne laisse ni fichier tronqué ni fichier existant détruit.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::CompressionError;
+
+// Nombre maximal de noms essayés par la politique `Rename`
+const MAX_RENAME_ATTEMPTS: u32 = 10_000;
+
+/// Comportement lorsque le fichier de sortie existe déjà
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// Échouer sans rien écrire
+    Fail,
+    /// Remplacer le fichier, seulement une fois la nouvelle sortie complète
+    #[default]
+    Overwrite,
+    /// Écrire sous un nom libre : `nom (1).ext`, `nom (2).ext`…
+    Rename,
+}
+
+/// Fichier de sortie écrit sous un nom temporaire ; supprimé s'il n'est pas validé
+pub(crate) struct AtomicFile {
+    writer: Option<BufWriter<File>>,
+    temp_path: PathBuf,
+    target: PathBuf,
+    policy: OverwritePolicy,
+}
+
+impl AtomicFile {
+    pub(crate) fn create(target: &Path, policy: OverwritePolicy) -> Result<Self> {
+        let target = resolve_target(target, policy)?;
+        let dir = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        // Même dossier que la cible : le renommage reste sur le même système de fichiers
+        loop {
+            let mut suffix = [0u8; 6];
+            getrandom::getrandom(&mut suffix)
+                .map_err(|e| CompressionError::IoError(io::Error::other(e.to_string())))?;
+            let temp_path = dir.join(format!(".{}.{}.tmp", name, hex::encode(suffix)));
+            match OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+                Ok(file) => {
+                    return Ok(Self { writer: Some(BufWriter::new(file)), temp_path, target, policy });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(CompressionError::IoError(e).into()),
+            }
+        }
+    }
+
+    /// Fichier temporaire, après écriture des données en attente
+    pub(crate) fn file(&mut self) -> io::Result<&File> {
+        let writer = self.writer()?;
+        writer.flush()?;
+        Ok(writer.get_ref())
+    }
+
+    /// Synchronise le fichier et le renomme à sa place ; renvoie le chemin final
+    pub(crate) fn commit(self) -> Result<PathBuf> {
+        self.finish()?.commit()
+    }
+
+    /// Synchronise et ferme le fichier temporaire, sans le renommer
+    pub(crate) fn finish(mut self) -> Result<PendingFile> {
+        let writer = self.writer.take().ok_or_else(closed)?;
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        drop(file);
+        Ok(PendingFile {
+            temp_path: std::mem::take(&mut self.temp_path),
+            target: std::mem::take(&mut self.target),
+            policy: self.policy,
+        })
+    }
+
+    fn writer(&mut self) -> io::Result<&mut BufWriter<File>> {
+        self.writer.as_mut().ok_or_else(closed)
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer()?.flush()
+    }
+}
+
+impl Seek for AtomicFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.writer()?.seek(pos)
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // Sortie abandonnée (erreur, annulation) : la cible n'a pas été touchée
+        if !self.target.as_os_str().is_empty() {
+            self.writer = None;
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Fichier temporaire complet et fermé, en attente de renommage ; supprimé s'il n'est
+/// pas validé
+pub(crate) struct PendingFile {
+    temp_path: PathBuf,
+    target: PathBuf,
+    policy: OverwritePolicy,
+}
+
+impl PendingFile {
+    /// Réécrit `data` à la position `offset` du fichier temporaire
+    pub(crate) fn patch(&self, offset: u64, data: &[u8]) -> io::Result<()> {
+        let mut file = OpenOptions::new().write(true).open(&self.temp_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+        file.sync_all()
+    }
+
+    /// Renomme le fichier à sa place ; renvoie le chemin final
+    pub(crate) fn commit(self) -> Result<PathBuf> {
+        let mut paths = commit_all(vec![self])?;
+        Ok(paths.swap_remove(0))
+    }
+}
+
+impl Drop for PendingFile {
+    fn drop(&mut self) {
+        if !self.target.as_os_str().is_empty() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Renomme tous les fichiers à leur place, ou aucun : en cas d'échec, les fichiers déjà
+/// renommés sont retirés et ceux qu'ils remplaçaient restaurés
+pub(crate) fn commit_all(mut files: Vec<PendingFile>) -> Result<Vec<PathBuf>> {
+    // Une cible a pu apparaître pendant l'écriture
+    if let Some(file) = files.iter().find(|f| f.policy != OverwritePolicy::Overwrite && f.target.exists()) {
+        return Err(already_exists(&file.target));
+    }
+
+    // Les fichiers remplacés sont mis de côté jusqu'à la fin
+    let mut backups: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut committed: Vec<PathBuf> = Vec::new();
+    let mut result = Ok(());
+    for file in &files {
+        if file.target.exists() {
+            match backup_path(&file.target).and_then(|backup| {
+                fs::rename(&file.target, &backup)?;
+                Ok(backup)
+            }) {
+                Ok(backup) => backups.push((file.target.clone(), backup)),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+    }
+    if result.is_ok() {
+        for file in &files {
+            if let Err(e) = fs::rename(&file.temp_path, &file.target) {
+                result = Err(e.into());
+                break;
+            }
+            committed.push(file.target.clone());
+        }
+    }
+
+    if let Err(e) = result {
+        for target in &committed {
+            let _ = fs::remove_file(target);
+        }
+        for (target, backup) in &backups {
+            let _ = fs::rename(backup, target);
+        }
+        return Err(e);
+    }
+    for (_, backup) in &backups {
+        let _ = fs::remove_file(backup);
+    }
+    if let Some(target) = committed.first() {
+        sync_parent(target);
+    }
+    Ok(files.iter_mut().map(|f| std::mem::take(&mut f.target)).collect())
+}
+
+/// Nom libre, voisin de `target`, où le mettre de côté
+fn backup_path(target: &Path) -> Result<PathBuf> {
+    let name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    loop {
+        let mut suffix = [0u8; 6];
+        getrandom::getrandom(&mut suffix)
+            .map_err(|e| CompressionError::IoError(io::Error::other(e.to_string())))?;
+        let path = target.with_file_name(format!(".{}.{}.bak", name, hex::encode(suffix)));
+        if !path.exists() {
+            return Ok(path);
+        }
+    }
+}
+
+/// Dossier de sortie rempli sous un nom temporaire ; supprimé s'il n'est pas validé
+///
+/// Sous `Overwrite`, un dossier existant est complété sur place, fichier par fichier :
+/// ses fichiers absents de l'archive ne sont jamais supprimés
+pub(crate) struct AtomicDir {
+    path: PathBuf,
+    target: PathBuf,
+    policy: OverwritePolicy,
+    temporary: bool,
+}
+
+impl AtomicDir {
+    pub(crate) fn create(target: &Path, policy: OverwritePolicy) -> Result<Self> {
+        let target = resolve_target(target, policy)?;
+        if target.is_dir() {
+            return Ok(Self { path: target.clone(), target, policy, temporary: false });
+        }
+        let dir = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        fs::create_dir_all(&dir)?;
+        let name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        loop {
+            let mut suffix = [0u8; 6];
+            getrandom::getrandom(&mut suffix)
+                .map_err(|e| CompressionError::IoError(io::Error::other(e.to_string())))?;
+            let path = dir.join(format!(".{}.{}.tmp", name, hex::encode(suffix)));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(Self { path, target, policy, temporary: true }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(CompressionError::IoError(e).into()),
+            }
+        }
+    }
+
+    /// Dossier où écrire
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Renomme le dossier temporaire à sa place ; renvoie le chemin final
+    pub(crate) fn commit(mut self) -> Result<PathBuf> {
+        if self.temporary {
+            if self.policy != OverwritePolicy::Overwrite && self.target.exists() {
+                return Err(already_exists(&self.target));
+            }
+            fs::rename(&self.path, &self.target)?;
+            sync_parent(&self.target);
+            self.temporary = false;
+        }
+        Ok(std::mem::take(&mut self.target))
+    }
+}
+
+impl Drop for AtomicDir {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Fichier de travail anonyme dans le dossier temporaire, supprimé à la fin
+pub(crate) struct ScratchFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl ScratchFile {
+    pub(crate) fn new() -> Result<Self> {
+        loop {
+            let mut suffix = [0u8; 6];
+            getrandom::getrandom(&mut suffix)
+                .map_err(|e| CompressionError::IoError(io::Error::other(e.to_string())))?;
+            let path = std::env::temp_dir().join(format!("ntk-{}.tmp", hex::encode(suffix)));
+            match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok(Self { file, path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(CompressionError::IoError(e).into()),
+            }
+        }
+    }
+
+    /// Recopie tout le contenu écrit ; renvoie sa taille
+    pub(crate) fn copy_to<W: Write>(&mut self, writer: &mut W) -> io::Result<u64> {
+        self.file.seek(SeekFrom::Start(0))?;
+        io::copy(&mut io::BufReader::new(&self.file), writer)
+    }
+}
+
+impl Write for ScratchFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for ScratchFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Chemin à écrire selon la politique, si la cible existe déjà
+pub(crate) fn resolve_target(target: &Path, policy: OverwritePolicy) -> Result<PathBuf> {
+    if !target.exists() || policy == OverwritePolicy::Overwrite {
+        return Ok(target.to_path_buf());
+    }
+    if policy == OverwritePolicy::Fail {
+        return Err(already_exists(target));
+    }
+
+    candidates(target)
+        .skip(1)
+        .find(|candidate| !candidate.exists())
+        .ok_or_else(|| already_exists(target))
+}
+
+/// Noms essayés par la politique `Rename` : `target`, puis `nom (1).ext`, `nom (2).ext`…
+pub(crate) fn candidates(target: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    let stem = target.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = target.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    std::iter::once(target.to_path_buf()).chain(
+        (1..=MAX_RENAME_ATTEMPTS).map(move |n| target.with_file_name(format!("{} ({}){}", stem, n, extension)))
+    )
+}
+
+/// Rend le renommage durable (meilleur effort, sans équivalent sous Windows)
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+pub(crate) fn already_exists(path: &Path) -> anyhow::Error {
+    CompressionError::IoError(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("Output already exists: {}", path.display())
+    )).into()
+}
+
+fn closed() -> io::Error {
+    io::Error::other("output file is already closed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_overwrite_policies() -> Result<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("out.txt");
+        fs::write(&target, b"old")?;
+
+        // Une sortie abandonnée ne touche pas au fichier existant
+        let mut output = AtomicFile::create(&target, OverwritePolicy::Overwrite)?;
+        output.write_all(b"partial")?;
+        drop(output);
+        assert_eq!(fs::read(&target)?, b"old");
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+
+        assert!(AtomicFile::create(&target, OverwritePolicy::Fail).is_err());
+
+        let mut output = AtomicFile::create(&target, OverwritePolicy::Rename)?;
+        output.write_all(b"renamed")?;
+        let renamed = output.commit()?;
+        assert_eq!(renamed, dir.path().join("out (1).txt"));
+        assert_eq!(fs::read(&renamed)?, b"renamed");
+
+        let mut output = AtomicFile::create(&target, OverwritePolicy::Overwrite)?;
+        output.write_all(b"new")?;
+        assert_eq!(output.commit()?, target);
+        assert_eq!(fs::read(&target)?, b"new");
+        assert_eq!(fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_all_rolls_back() -> Result<()> {
+        let dir = tempdir()?;
+        let (first, second) = (dir.path().join("a.001"), dir.path().join("a.002"));
+        fs::write(&first, b"old")?;
+
+        let mut files = Vec::new();
+        for target in [&first, &second] {
+            let mut output = AtomicFile::create(target, OverwritePolicy::Overwrite)?;
+            output.write_all(b"new")?;
+            files.push(output.finish()?);
+        }
+        // Le second renommage échoue : le premier fichier retrouve son contenu
+        fs::remove_file(&files[1].temp_path)?;
+        assert!(commit_all(files).is_err());
+        assert_eq!(fs::read(&first)?, b"old");
+        assert_eq!(fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,636 @@
+//! Parité Reed-Solomon contre la corruption des supports (version 10+)
+//!
+//! La zone des trames, trame de fin comprise (v14+ : index compris), est découpée en
+//! fragments de 64 Kio regroupés par 64. Chaque groupe reçoit des fragments de parité,
+//! écrits après cette zone. L'empreinte de chaque fragment permet de détecter un fragment
+//! endommagé, puis de le reconstruire à partir des autres fragments de son groupe.
+//!
+//! v14+ : paramètres et empreintes forment un enregistrement en clair, vérifié par sa
+//! propre empreinte et écrit avant et après la section de parité ; la réparation ne
+//! dépend donc pas de l'index. v10 à v13 : ils sont dans l'index, qui n'est pas protégé.
+//! L'en-tête n'est jamais protégé.
+
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use serde::{Deserialize, Serialize};
+
+use crate::output::{AtomicFile, OverwritePolicy, ScratchFile};
+use crate::volume::ArchiveReader;
+use crate::{
+    frame_prefix_size, from_io_error, into_io_error, CompressionError, Compressor, MAX_FOOTER_SIZE, TRAILER_SIZE,
+    VERSION_PARITY, VERSION_PARITY_RECORD,
+};
+
+const SHARD_SIZE: usize = 64 * 1024;
+const GROUP_SHARDS: usize = 64;
+// Empreinte BLAKE3 tronquée : il s'agit de détecter la corruption, pas une attaque
+const HASH_SIZE: usize = 16;
+// Limites acceptées à la lecture (protection contre un index forgé)
+const MAX_SHARD_SIZE: u32 = 16 * 1024 * 1024;
+const MAX_GROUP_SHARDS: u32 = 128;
+// Enregistrement de la parité (v14+) : JSON, BLAKE3 du JSON, taille du JSON (u32 LE), magique
+const RECORD_MAGIC: &[u8] = b"NTKP";
+const RECORD_SUFFIX_SIZE: u64 = 32 + 4 + 4;
+// Recherche d'un enregistrement dans l'archive, par morceaux
+const SCAN_CHUNK: u64 = 1024 * 1024;
+
+/// Résultat de `Compressor::repair`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepairReport {
+    /// Index des blocs recouverts par un fragment reconstruit
+    pub repaired_blocks: Vec<u32>,
+    /// Fragments de parité reconstruits
+    pub repaired_parity_shards: usize,
+}
+
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.repaired_blocks.is_empty() && self.repaired_parity_shards == 0
+    }
+}
+
+impl Compressor {
+    /// Vérifie chaque fragment d'une archive protégée par parité et reconstruit ceux qui
+    /// sont endommagés ; l'archive n'est réécrite que si un fragment a été reconstruit
+    pub fn repair<P: AsRef<Path>>(&self, archive: P) -> Result<RepairReport> {
+        let path = archive.as_ref();
+        // L'archive est réécrite d'un seul tenant
+        let input = match self.open_reader(path)? {
+            ArchiveReader::File(file) => file,
+            ArchiveReader::Volumes(_) => return Err(CompressionError::CompressionError(
+                "repair is not supported for multi-volume archives".into()
+            ).into()),
+            ArchiveReader::Memory(_) => return Err(CompressionError::CompressionError(
+                "repair is not supported for archives hidden in an image".into()
+            ).into()),
+        };
+        let mut input = BufReader::new(input);
+        let parsed = self.read_header(&mut input)?;
+        let key = self.archive_key(&parsed)?;
+        let parity = match parsed.header.version {
+            version if version >= VERSION_PARITY_RECORD => read_parity_record(&mut input, parsed.raw.len() as u64, true)?,
+            version if version >= VERSION_PARITY => self.read_footer(&parsed, &key, &mut input)?.parity,
+            _ => None,
+        };
+        let Some(parity) = parity else {
+            return Err(CompressionError::IntegrityError("archive has no parity data".into()).into());
+        };
+
+        let mut reader = ParityReader::new(input, Some(parity), true);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut output = AtomicFile::create(path, OverwritePolicy::Overwrite)?;
+        io::copy(&mut reader, &mut output).map_err(from_io_error)?;
+        // v14+ : l'index, relu une fois reconstruit, situe les blocs réparés
+        let footer = self.read_footer(&parsed, &key, &mut reader)?;
+
+        let prefix_size = frame_prefix_size(parsed.header.version);
+        let mut report = RepairReport::default();
+        for &(offset, len, data) in reader.repaired() {
+            if !data {
+                report.repaired_parity_shards += 1;
+                continue;
+            }
+            // Blocs dont la trame (préfixe compris) chevauche le fragment
+            let damaged = footer.blocks.iter().enumerate().filter(|(_, entry)| {
+                entry.offset < offset + len as u64 && offset < entry.offset + prefix_size + entry.compressed_size as u64
+            });
+            report.repaired_blocks.extend(damaged.map(|(index, _)| index as u32));
+        }
+        report.repaired_blocks.sort_unstable();
+        report.repaired_blocks.dedup();
+
+        if !reader.repaired().is_empty() {
+            output.commit()?;
+        }
+        Ok(report)
+    }
+}
+
+/// Paramètres et empreintes de la parité, enregistrés à part (v14+) ou dans l'index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ParityInfo {
+    /// Fragments de parité, en pourcentage des fragments de données de chaque groupe
+    redundancy: u8,
+    shard_size: u32,
+    group_shards: u32,
+    /// Zone protégée : des trames jusqu'à la trame de fin incluse (v14+ : jusqu'à l'index)
+    start: u64,
+    len: u64,
+    /// Taille de l'index, qui termine la zone protégée (v14+)
+    #[serde(default)]
+    footer_len: u64,
+    /// Octets entre la zone protégée et la section de parité : la première copie de
+    /// l'enregistrement (v14+)
+    #[serde(skip)]
+    gap: u64,
+    /// Empreintes (hex) des fragments de données, complétés par des zéros
+    data_hashes: Vec<String>,
+    parity_hashes: Vec<String>,
+}
+
+impl ParityInfo {
+    pub(crate) fn validate(&self) -> Result<()> {
+        let valid = (1..=100).contains(&self.redundancy)
+            && (1..=MAX_SHARD_SIZE).contains(&self.shard_size)
+            && (1..=MAX_GROUP_SHARDS).contains(&self.group_shards)
+            && self.footer_len <= self.len
+            && self.data_hashes.len() as u64 == self.len.div_ceil(self.shard_size as u64)
+            && self.parity_hashes.len() == self.parity_total();
+        if !valid {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+        Ok(())
+    }
+
+    /// Taille de la section de parité
+    pub(crate) fn parity_len(&self) -> u64 {
+        self.parity_hashes.len() as u64 * self.shard_size as u64
+    }
+
+    fn shard_size(&self) -> u64 {
+        self.shard_size as u64
+    }
+
+    fn data_shards(&self) -> usize {
+        self.data_hashes.len()
+    }
+
+    fn groups(&self) -> usize {
+        self.data_shards().div_ceil(self.group_shards as usize)
+    }
+
+    fn group_data_shards(&self, group: usize) -> usize {
+        let group_shards = self.group_shards as usize;
+        group_shards.min(self.data_shards() - group * group_shards)
+    }
+
+    fn parity_total(&self) -> usize {
+        (0..self.groups()).map(|group| parity_count(self.group_data_shards(group), self.redundancy)).sum()
+    }
+
+    /// Position et taille de l'index dans l'archive (v14+)
+    pub(crate) fn footer_start(&self) -> u64 {
+        self.start + self.len - self.footer_len
+    }
+
+    pub(crate) fn footer_len(&self) -> u64 {
+        self.footer_len
+    }
+
+    /// Taille d'une copie de l'enregistrement (v14+)
+    pub(crate) fn record_len(&self) -> u64 {
+        self.gap
+    }
+
+    fn parity_start(&self) -> u64 {
+        self.start + self.len + self.gap
+    }
+
+    fn parity_end(&self) -> u64 {
+        self.parity_start() + self.parity_len()
+    }
+
+    /// Position, taille et empreinte attendue d'un fragment (`slot` : données puis parité)
+    fn shard(&self, group: usize, slot: usize) -> (u64, usize, &str) {
+        let data_shards = self.group_data_shards(group);
+        if slot < data_shards {
+            let index = group * self.group_shards as usize + slot;
+            let offset = index as u64 * self.shard_size();
+            let len = (self.len - offset).min(self.shard_size()) as usize;
+            (self.start + offset, len, &self.data_hashes[index])
+        } else {
+            // Tous les groupes précédents sont complets
+            let index = group * parity_count(self.group_shards as usize, self.redundancy) + slot - data_shards;
+            (self.parity_start() + index as u64 * self.shard_size(), self.shard_size as usize, &self.parity_hashes[index])
+        }
+    }
+
+    /// Fragment contenant `position`, s'il est protégé
+    fn locate(&self, position: u64, with_parity: bool) -> Option<(usize, usize)> {
+        if position < self.start {
+            return None;
+        }
+        let relative = position - self.start;
+        if relative < self.len {
+            let index = (relative / self.shard_size()) as usize;
+            let group_shards = self.group_shards as usize;
+            return Some((index / group_shards, index % group_shards));
+        }
+
+        if !with_parity || position < self.parity_start() || position >= self.parity_end() {
+            return None;
+        }
+        let index = ((position - self.parity_start()) / self.shard_size()) as usize;
+        let full_group = parity_count(self.group_shards as usize, self.redundancy);
+        let group = index / full_group;
+        Some((group, self.group_data_shards(group) + index % full_group))
+    }
+}
+
+/// Calcule la parité des trames au fil de leur écriture
+pub(crate) struct ParityEncoder {
+    redundancy: u8,
+    start: u64,
+    len: u64,
+    /// Fragments de données du groupe en cours
+    group: Vec<Vec<u8>>,
+    data_hashes: Vec<String>,
+    parity_hashes: Vec<String>,
+    /// Fragments de parité, écrits après la trame de fin
+    scratch: ScratchFile,
+}
+
+impl ParityEncoder {
+    /// `start` : position de la première trame dans l'archive
+    pub(crate) fn new(redundancy: u8, start: u64) -> Result<Self> {
+        if !(1..=100).contains(&redundancy) {
+            return Err(CompressionError::CompressionError("Parity redundancy must be between 1 and 100 %".into()).into());
+        }
+        Ok(Self {
+            redundancy,
+            start,
+            len: 0,
+            group: Vec::new(),
+            data_hashes: Vec::new(),
+            parity_hashes: Vec::new(),
+            scratch: ScratchFile::new()?,
+        })
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) -> Result<()> {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            if self.group.last().is_none_or(|shard| shard.len() == SHARD_SIZE) {
+                if self.group.len() == GROUP_SHARDS {
+                    self.encode_group()?;
+                }
+                self.group.push(Vec::with_capacity(SHARD_SIZE));
+            }
+            let shard = self.group.last_mut().unwrap();
+            let take = (SHARD_SIZE - shard.len()).min(data.len());
+            shard.extend_from_slice(&data[..take]);
+            data = &data[take..];
+        }
+        Ok(())
+    }
+
+    /// Écrit la section de parité entre les deux copies de son enregistrement ; renvoie la
+    /// taille écrite. `footer_len` : taille de l'index, dernier élément protégé
+    pub(crate) fn finish<W: Write>(mut self, writer: &mut W, footer_len: u64) -> Result<u64> {
+        if !self.group.is_empty() {
+            self.encode_group()?;
+        }
+        let record = encode_record(&ParityInfo {
+            redundancy: self.redundancy,
+            shard_size: SHARD_SIZE as u32,
+            group_shards: GROUP_SHARDS as u32,
+            start: self.start,
+            len: self.len,
+            footer_len,
+            gap: 0,
+            data_hashes: std::mem::take(&mut self.data_hashes),
+            parity_hashes: std::mem::take(&mut self.parity_hashes),
+        })?;
+        writer.write_all(&record)?;
+        let written = self.scratch.copy_to(writer)?;
+        writer.write_all(&record)?;
+        Ok(written + 2 * record.len() as u64)
+    }
+
+    fn encode_group(&mut self) -> Result<()> {
+        let data_shards = self.group.len();
+        let parity_shards = parity_count(data_shards, self.redundancy);
+
+        let mut shards = std::mem::take(&mut self.group);
+        for shard in &mut shards {
+            shard.resize(SHARD_SIZE, 0);
+            self.data_hashes.push(shard_hash(shard));
+        }
+        shards.resize(data_shards + parity_shards, vec![0u8; SHARD_SIZE]);
+        ReedSolomon::new(data_shards, parity_shards)
+            .and_then(|rs| rs.encode(&mut shards))
+            .map_err(|e| CompressionError::CompressionError(format!("Parity encoding failed: {}", e)))?;
+
+        for shard in &shards[data_shards..] {
+            self.parity_hashes.push(shard_hash(shard));
+            self.scratch.write_all(shard)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lecture d'une archive en vérifiant chaque fragment protégé et en reconstruisant
+/// ceux qui sont endommagés ; sans parité, simple intermédiaire
+pub(crate) struct ParityReader<R> {
+    inner: R,
+    parity: Option<ParityInfo>,
+    /// Vérifier (et reconstruire) aussi les fragments de parité
+    with_parity: bool,
+    position: u64,
+    inner_position: Option<u64>,
+    /// Fragments vérifiés ou reconstruits : (position, contenu)
+    cached: Vec<(u64, Vec<u8>)>,
+    /// Fragments reconstruits : (position, taille, fragment de données)
+    repaired: Vec<(u64, usize, bool)>,
+}
+
+impl<R: Read + Seek> ParityReader<R> {
+    pub(crate) fn new(inner: R, parity: Option<ParityInfo>, with_parity: bool) -> Self {
+        Self { inner, parity, with_parity, position: 0, inner_position: None, cached: Vec::new(), repaired: Vec::new() }
+    }
+
+    pub(crate) fn repaired(&self) -> &[(u64, usize, bool)] {
+        &self.repaired
+    }
+
+    fn read_raw(&mut self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        if self.inner_position != Some(offset) {
+            self.inner.seek(SeekFrom::Start(offset))?;
+        }
+        let read = self.inner.read(buf)?;
+        self.inner_position = Some(offset + read as u64);
+        Ok(read)
+    }
+
+    /// Fragment lu tel quel ; `None` s'il est incomplet ou ne correspond pas à son empreinte
+    fn read_shard(&mut self, offset: u64, len: usize, hash: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut shard = vec![0u8; len];
+        let mut filled = 0;
+        while filled < len {
+            match self.read_raw(offset + filled as u64, &mut shard[filled..])? {
+                0 => return Ok(None),
+                read => filled += read,
+            }
+        }
+        let parity = self.parity.as_ref().unwrap();
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&shard);
+        hasher.update(&vec![0u8; parity.shard_size as usize - len]);
+        Ok((hex::encode(&hasher.finalize().as_bytes()[..HASH_SIZE]) == hash).then_some(shard))
+    }
+
+    fn load(&mut self, group: usize, slot: usize) -> Result<()> {
+        let parity = self.parity.clone().unwrap();
+        let (offset, len, hash) = parity.shard(group, slot);
+        if let Some(shard) = self.read_shard(offset, len, hash)? {
+            self.cached = vec![(offset, shard)];
+            return Ok(());
+        }
+        self.reconstruct(&parity, group)
+    }
+
+    fn reconstruct(&mut self, parity: &ParityInfo, group: usize) -> Result<()> {
+        let data_shards = parity.group_data_shards(group);
+        let parity_shards = parity_count(data_shards, parity.redundancy);
+        let shard_size = parity.shard_size as usize;
+
+        let mut shards = Vec::with_capacity(data_shards + parity_shards);
+        let mut damaged = Vec::new();
+        for slot in 0..data_shards + parity_shards {
+            let (offset, len, hash) = parity.shard(group, slot);
+            let shard = self.read_shard(offset, len, hash)?;
+            if shard.is_none() {
+                damaged.push(slot);
+            }
+            shards.push(shard.map(|mut shard| {
+                shard.resize(shard_size, 0);
+                shard
+            }));
+        }
+        if damaged.len() > parity_shards {
+            return Err(CompressionError::IntegrityError(format!(
+                "{} damaged shards in parity group {}, at most {} can be repaired",
+                damaged.len(), group, parity_shards
+            )).into());
+        }
+
+        ReedSolomon::new(data_shards, parity_shards)
+            .and_then(|rs| rs.reconstruct(&mut shards))
+            .map_err(|e| CompressionError::IntegrityError(format!("parity group {} cannot be repaired: {}", group, e)))?;
+
+        self.cached.clear();
+        for (slot, shard) in shards.into_iter().enumerate() {
+            let (offset, len, _) = parity.shard(group, slot);
+            let mut shard = shard.unwrap_or_default();
+            shard.truncate(len);
+            // Un groupe peut être reconstruit plusieurs fois (relecture, parité lue en fin)
+            if damaged.contains(&slot) && !self.repaired.iter().any(|&(repaired, ..)| repaired == offset) {
+                self.repaired.push((offset, len, slot < data_shards));
+            }
+            self.cached.push((offset, shard));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for ParityReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.parity.is_none() {
+            return self.inner.read(buf);
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let position = self.position;
+            let cached = self.cached.iter()
+                .find(|(offset, shard)| (*offset..*offset + shard.len() as u64).contains(&position));
+            if let Some((offset, shard)) = cached {
+                let start = (position - offset) as usize;
+                let len = buf.len().min(shard.len() - start);
+                buf[..len].copy_from_slice(&shard[start..start + len]);
+                self.position += len as u64;
+                return Ok(len);
+            }
+
+            let parity = self.parity.as_ref().unwrap();
+            match parity.locate(position, self.with_parity) {
+                Some((group, slot)) => self.load(group, slot).map_err(into_io_error)?,
+                None => {
+                    // Hors zone protégée : lecture directe jusqu'à la zone suivante
+                    let boundary = [parity.start, parity.start + parity.len, parity.parity_start(), parity.parity_end()]
+                        .into_iter()
+                        .find(|&boundary| boundary > position);
+                    let len = match boundary {
+                        Some(boundary) => buf.len().min((boundary - position) as usize),
+                        None => buf.len(),
+                    };
+                    let read = self.read_raw(position, &mut buf[..len])?;
+                    self.position += read as u64;
+                    return Ok(read);
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for ParityReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if self.parity.is_none() {
+            return self.inner.seek(pos);
+        }
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(_) => {
+                let position = self.inner.seek(pos)?;
+                self.inner_position = Some(position);
+                position
+            }
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?,
+        };
+        Ok(self.position)
+    }
+}
+
+fn encode_record(info: &ParityInfo) -> Result<Vec<u8>> {
+    let mut record = serde_json::to_vec(info)?;
+    let len = record.len() as u32;
+    let hash = blake3::hash(&record);
+    record.extend_from_slice(hash.as_bytes());
+    record.extend_from_slice(&len.to_le_bytes());
+    record.extend_from_slice(RECORD_MAGIC);
+    Ok(record)
+}
+
+/// Enregistrement de la parité qui se termine à `end`, s'il est intact
+pub(crate) fn record_ending_at<R: Read + Seek>(input: &mut R, end: u64) -> io::Result<Option<ParityInfo>> {
+    if end < RECORD_SUFFIX_SIZE {
+        return Ok(None);
+    }
+    let mut suffix = [0u8; RECORD_SUFFIX_SIZE as usize];
+    input.seek(SeekFrom::Start(end - RECORD_SUFFIX_SIZE))?;
+    input.read_exact(&mut suffix)?;
+    let len = u32::from_le_bytes(suffix[32..36].try_into().unwrap()) as u64;
+    if &suffix[36..] != RECORD_MAGIC || len > MAX_FOOTER_SIZE || len + RECORD_SUFFIX_SIZE > end {
+        return Ok(None);
+    }
+
+    let mut json = vec![0u8; len as usize];
+    input.seek(SeekFrom::Start(end - RECORD_SUFFIX_SIZE - len))?;
+    input.read_exact(&mut json)?;
+    if blake3::hash(&json).as_bytes()[..] != suffix[..32] {
+        return Ok(None);
+    }
+    let Ok(mut info) = serde_json::from_slice::<ParityInfo>(&json) else {
+        return Ok(None);
+    };
+    info.gap = len + RECORD_SUFFIX_SIZE;
+    Ok(info.validate().is_ok().then_some(info))
+}
+
+/// Parité d'une archive v14+ : copie de l'enregistrement qui précède le trailer, sinon
+/// (`scan`) première copie intacte trouvée en parcourant l'archive
+pub(crate) fn read_parity_record<R: Read + Seek>(input: &mut R, data_start: u64, scan: bool) -> io::Result<Option<ParityInfo>> {
+    let file_size = input.seek(SeekFrom::End(0))?;
+    // Une copie n'est retenue qu'à l'une des deux places qu'elle désigne
+    let placed = |info: &ParityInfo, end: u64| {
+        info.start == data_start && (end == info.parity_start() || end == info.parity_end() + info.gap)
+    };
+    if let Some(end) = file_size.checked_sub(TRAILER_SIZE) {
+        if let Some(info) = record_ending_at(input, end)?.filter(|info| placed(info, end)) {
+            return Ok(Some(info));
+        }
+    }
+    if !scan {
+        return Ok(None);
+    }
+    scan_records(input, data_start, file_size, placed)
+}
+
+/// Première copie intacte de l'enregistrement qui se termine entre `from` et `to` à une
+/// place acceptée par `placed`
+pub(crate) fn scan_records<R: Read + Seek>(
+    input: &mut R,
+    from: u64,
+    to: u64,
+    placed: impl Fn(&ParityInfo, u64) -> bool,
+) -> io::Result<Option<ParityInfo>> {
+    let mut position = from;
+    let mut chunk = Vec::new();
+    while position < to {
+        chunk.clear();
+        input.seek(SeekFrom::Start(position))?;
+        input.by_ref().take(SCAN_CHUNK).read_to_end(&mut chunk)?;
+        let candidates: Vec<u64> = chunk.windows(RECORD_MAGIC.len())
+            .enumerate()
+            .filter(|(_, window)| *window == RECORD_MAGIC)
+            .map(|(at, _)| position + (at + RECORD_MAGIC.len()) as u64)
+            .collect();
+        for end in candidates {
+            if let Some(info) = record_ending_at(input, end)?.filter(|info| placed(info, end)) {
+                return Ok(Some(info));
+            }
+        }
+        // Les morceaux se chevauchent pour ne pas couper un magique
+        if chunk.len() < SCAN_CHUNK as usize {
+            break;
+        }
+        position += (chunk.len() - (RECORD_MAGIC.len() - 1)) as u64;
+    }
+    Ok(None)
+}
+
+fn parity_count(data_shards: usize, redundancy: u8) -> usize {
+    (data_shards * redundancy as usize).div_ceil(100).max(1)
+}
+
+fn shard_hash(shard: &[u8]) -> String {
+    hex::encode(&blake3::hash(shard).as_bytes()[..HASH_SIZE])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parity_reconstructs_damaged_shards() -> Result<()> {
+        let mut data = vec![0u8; SHARD_SIZE * 70 + 123];
+        getrandom::getrandom(&mut data)?;
+        let prefix = b"header";
+
+        let mut archive = prefix.to_vec();
+        let mut encoder = ParityEncoder::new(5, prefix.len() as u64)?;
+        archive.extend_from_slice(&data);
+        encoder.update(&data)?;
+        let written = encoder.finish(&mut archive, 0)?;
+        let parity = record_ending_at(&mut Cursor::new(&archive), archive.len() as u64)?.unwrap();
+        archive.extend_from_slice(&[0u8; TRAILER_SIZE as usize]);
+        // 64 + 6 fragments de données : 4 + 1 fragments de parité, entre deux enregistrements
+        assert_eq!(written, 5 * SHARD_SIZE as u64 + 2 * parity.record_len());
+
+        // Premier groupe : trois fragments de données et un de parité endommagés ;
+        // second groupe : un fragment de données
+        let mut damaged = archive.clone();
+        for shard in [0, 10, 63, 69] {
+            damaged[prefix.len() + shard * SHARD_SIZE + 7] ^= 0xFF;
+        }
+        damaged[prefix.len() + data.len() + parity.record_len() as usize + 3] ^= 0x01;
+
+        // Seconde copie de l'enregistrement abîmée : la première est retrouvée
+        let mut records = archive.clone();
+        records[archive.len() - TRAILER_SIZE as usize - 50] ^= 0xFF;
+        assert!(read_parity_record(&mut Cursor::new(&records), prefix.len() as u64, false)?.is_none());
+        let found = read_parity_record(&mut Cursor::new(&records), prefix.len() as u64, true)?.unwrap();
+        assert_eq!(found.parity_hashes, parity.parity_hashes);
+
+        let mut reader = ParityReader::new(Cursor::new(damaged.clone()), Some(parity.clone()), true);
+        let mut repaired = Vec::new();
+        reader.read_to_end(&mut repaired)?;
+        assert_eq!(repaired, archive);
+        assert_eq!(reader.repaired().len(), 5);
+        assert_eq!(reader.repaired().iter().filter(|(_, _, data)| *data).count(), 4);
+
+        // Au-delà de la redondance du groupe, l'erreur est signalée
+        damaged[prefix.len() + 30 * SHARD_SIZE] ^= 0xFF;
+        let mut reader = ParityReader::new(Cursor::new(damaged), Some(parity), false);
+        let error = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        Ok(())
+    }
+}
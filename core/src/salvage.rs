@@ -0,0 +1,387 @@
+//! Récupération au mieux d'une archive endommagée
+//!
+//! Les blocs illisibles sont sautés : la lecture reprend à la trame suivante de l'index
+//! s'il est lisible, sinon au marqueur de synchronisation suivant (v11+). Les plages
+//! des données d'origine perdues sont rapportées, et remplies de zéros ou omises.
+
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::output::AtomicFile;
+use crate::parity::ParityReader;
+use crate::volume::ArchiveReader;
+use crate::{
+    block_error, frame_prefix_size, from_io_error, ArchiveFooter, ChunkRef, CompressionError, Compressor, FramePrefix,
+    ParsedHeader, SYNC_MARKER, VERSION_BLOCK_INDEX, VERSION_CHECKSUMS, VERSION_SYNC,
+};
+
+// Taille des morceaux lus pour rechercher le marqueur suivant
+const SCAN_CHUNK: u64 = 1024 * 1024;
+
+/// Traitement des plages perdues dans la sortie
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GapFill {
+    /// Remplacer chaque plage perdue par des zéros : les positions sont conservées
+    #[default]
+    Zeros,
+    /// Ne rien écrire : les données récupérées sont mises bout à bout
+    Omit,
+}
+
+/// Plage `[start, end)` des données d'origine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Résultat de `Compressor::salvage`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SalvageReport {
+    /// Chemin réellement écrit (voir `OverwritePolicy::Rename`)
+    pub output_path: String,
+    pub recovered_blocks: u32,
+    pub recovered_bytes: u64,
+    /// Plages perdues, dans l'ordre ; sans index ni taille d'origine, une perte en fin
+    /// d'archive ne peut pas être détectée
+    pub lost_ranges: Vec<ByteRange>,
+}
+
+/// Issue de la lecture d'une trame
+enum Candidate {
+    /// Bloc décodé : index, position d'origine, contenu et fin de la trame dans l'archive
+    Block(u32, u64, Vec<u8>, u64),
+    /// Trame de fin ou fin du fichier
+    End,
+    /// Trame illisible, avec la raison si le bloc a pu être lu
+    Damaged(Option<anyhow::Error>),
+}
+
+/// Index lisible : place et taille d'origine de chaque bloc
+struct KnownBlocks<'a> {
+    footer: &'a ArchiveFooter,
+    starts: Vec<u64>,
+}
+
+/// Lecture d'une archive endommagée, trame par trame
+struct Salvager<'a, R> {
+    compressor: &'a Compressor,
+    parsed: &'a ParsedHeader,
+    key: &'a [u8],
+    known: Option<KnownBlocks<'a>>,
+    input: R,
+    file_size: u64,
+}
+
+impl Compressor {
+    /// Décompresse au mieux une archive endommagée : les blocs absents, altérés ou
+    /// impossibles à déchiffrer sont sautés et leurs plages d'origine rapportées.
+    /// L'en-tête doit rester lisible ; les archives multi-fichiers et incrémentales ne sont
+    /// pas prises en charge.
+    pub fn salvage<P: AsRef<Path>>(&self, input: P, output: P, fill: GapFill) -> Result<SalvageReport> {
+        let mut input = BufReader::new(ArchiveReader::open(input.as_ref())?);
+        let parsed = self.read_header(&mut input)?;
+        if parsed.header.multi_entry {
+            return Err(CompressionError::CompressionError(
+                "salvage is not supported for multi-file archives".into()
+            ).into());
+        }
+        if parsed.header.base.is_some() {
+            return Err(CompressionError::CompressionError(
+                "salvage is not supported for incremental archives".into()
+            ).into());
+        }
+        let key = self.archive_key(&parsed)?;
+
+        // L'index, s'il est intact, donne la place et l'empreinte de chaque bloc
+        let footer = match parsed.header.version {
+            version if version >= VERSION_BLOCK_INDEX => self.read_footer(&parsed, &key, &mut input).ok(),
+            _ => None,
+        };
+        // Suite des blocs : index, position et taille d'origine, position de la trame
+        let layout: Option<Vec<_>> = footer.as_ref().and_then(|footer| {
+            let layout = footer.layout(&[]).ok()?;
+            Some(layout.into_iter().filter_map(|(chunk, start, size)| match chunk {
+                ChunkRef::Block(index) => Some((index as u32, start, size, footer.blocks[index].offset)),
+                ChunkRef::Base(_) => None,
+            }).collect())
+        });
+        let footer = footer.filter(|_| layout.is_some());
+        let mut input = ParityReader::new(input, footer.as_ref().and_then(|f| f.parity.clone()), false);
+        let file_size = input.seek(SeekFrom::End(0))?;
+        let mut salvager = Salvager {
+            compressor: self,
+            parsed: &parsed,
+            key: &key,
+            known: footer.as_ref().map(|footer| KnownBlocks { footer, starts: footer.block_starts(&[]).unwrap_or_default() }),
+            input,
+            file_size,
+        };
+
+        let mut output = AtomicFile::create(output.as_ref(), self.options.overwrite)?;
+        let mut report = SalvageReport::default();
+        let mut last_error = None;
+        let mut offset = parsed.raw.len() as u64;
+        let mut next_index = 0u32;
+        let mut data_position = 0u64;
+
+        // Index lisible : chaque bloc de la suite (déduplication comprise) est lu à sa place
+        for &(index, start, _, frame_offset) in layout.iter().flatten() {
+            self.check_cancelled()?;
+            match salvager.frame(frame_offset, index, start) {
+                Candidate::Block(_, _, data, _) => {
+                    lose(&mut output, &mut report, data_position, start, fill)?;
+                    output.write_all(&data)?;
+                    report.recovered_blocks += 1;
+                    report.recovered_bytes += data.len() as u64;
+                    data_position = start + data.len() as u64;
+                }
+                Candidate::Damaged(Some(e)) => last_error = Some(e),
+                _ => {}
+            }
+        }
+
+        while layout.is_none() {
+            self.check_cancelled()?;
+            match salvager.frame(offset, next_index, data_position) {
+                // Les blocs sont dans l'ordre : un bloc qui recule est un faux marqueur
+                Candidate::Block(index, position, data, frame_end) if index >= next_index && position >= data_position => {
+                    lose(&mut output, &mut report, data_position, position, fill)?;
+                    output.write_all(&data)?;
+                    report.recovered_blocks += 1;
+                    report.recovered_bytes += data.len() as u64;
+                    data_position = position + data.len() as u64;
+                    next_index = index.saturating_add(1);
+                    offset = frame_end;
+                }
+                Candidate::End => break,
+                candidate => {
+                    if let Candidate::Damaged(Some(e)) = candidate {
+                        last_error = Some(e);
+                    }
+                    match salvager.resync(offset)? {
+                        Some(next) => offset = next,
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        // Fin des données d'origine : index, sinon taille enregistrée dans l'en-tête
+        let total = match &layout {
+            Some(layout) => layout.last().map(|&(_, start, size, _)| start + size),
+            None => Some(parsed.header.original_size),
+        };
+        if let Some(total) = total {
+            lose(&mut output, &mut report, data_position, total, fill)?;
+        }
+
+        // Rien de récupérable (mauvais mot de passe, archive méconnaissable) : pas de sortie
+        if report.recovered_blocks == 0 && !report.lost_ranges.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                CompressionError::IntegrityError("no block could be recovered".into()).into()
+            }));
+        }
+        report.output_path = output.commit()?.to_string_lossy().into_owned();
+        Ok(report)
+    }
+}
+
+impl<R: Read + Seek> Salvager<'_, R> {
+    /// Lit et décode la trame à `offset` ; `next_index` et `data_position` servent de
+    /// repères pour les archives sans index ni marqueur de synchronisation
+    fn frame(&mut self, offset: u64, next_index: u32, data_position: u64) -> Candidate {
+        let version = self.parsed.header.version;
+        let prefix_size = frame_prefix_size(version);
+        if offset + prefix_size > self.file_size {
+            return Candidate::End;
+        }
+        let Some(prefix) = self.prefix_at(offset) else {
+            return Candidate::Damaged(None);
+        };
+        if prefix.size == 0 && version >= VERSION_BLOCK_INDEX {
+            return Candidate::End;
+        }
+
+        // Place du bloc : index, sinon préfixe (v11+), sinon suite de la lecture
+        let (index, position, is_last) = match &self.known {
+            Some(known) => match known.footer.blocks.binary_search_by_key(&offset, |entry| entry.offset) {
+                Ok(index) if known.footer.blocks[index].compressed_size == prefix.size => {
+                    (index as u32, known.starts[index], Some(index + 1 == known.footer.blocks.len()))
+                }
+                _ => return Candidate::Damaged(None),
+            },
+            None => {
+                let (index, position) = prefix.sync.unwrap_or((next_index, data_position));
+                (index, position, None)
+            }
+        };
+
+        let frame_end = offset + prefix_size + prefix.size as u64;
+        if prefix.size == 0 || prefix.size as usize > self.compressor.max_frame_size(&self.parsed.header) || frame_end > self.file_size {
+            return Candidate::Damaged(None);
+        }
+        let data = match read_at(&mut self.input, offset + prefix_size, prefix.size as usize) {
+            Ok(data) => data,
+            Err(e) => return Candidate::Damaged(Some(from_io_error(e))),
+        };
+
+        // Sans index, le dernier bloc est celui que suit la trame de fin (ou la fin du fichier)
+        let is_last = match is_last {
+            Some(is_last) => is_last,
+            None if version >= VERSION_BLOCK_INDEX => self.prefix_at(frame_end).is_some_and(|next| next.size == 0),
+            None => frame_end == self.file_size,
+        };
+
+        let (compressor, parsed, key) = (self.compressor, self.parsed, self.key);
+        let decoded = compressor.open_block(parsed, key, index, is_last, &data)
+            .or_else(|e| match self.known {
+                // La trame suivante est peut-être illisible : le bloc était alors peut-être le dernier
+                None if parsed.header.encrypted => compressor.open_block(parsed, key, index, !is_last, &data).map_err(|_| e),
+                _ => Err(e),
+            });
+        let decoded = match decoded {
+            Ok(decoded) => decoded,
+            Err(e) => return Candidate::Damaged(Some(block_error(e, index, offset))),
+        };
+        if let Some(known) = &self.known {
+            let entry = &known.footer.blocks[index as usize];
+            let matches = decoded.len() as u64 == entry.size
+                && (version < VERSION_CHECKSUMS || known.footer.verify_block(index as usize, &blake3::hash(&decoded)).is_ok());
+            if !matches {
+                return Candidate::Damaged(None);
+            }
+        }
+        Candidate::Block(index, position, decoded, frame_end)
+    }
+
+    /// Position de la prochaine trame à essayer après une trame illisible à `offset`
+    /// (archive sans index lisible)
+    fn resync(&mut self, offset: u64) -> Result<Option<u64>> {
+        if self.parsed.header.version < VERSION_SYNC {
+            // Sans marqueur ni index, la suite de l'archive ne peut pas être retrouvée
+            return Ok(None);
+        }
+        Ok(find_marker(&mut self.input, offset + 1)?)
+    }
+
+    fn prefix_at(&mut self, offset: u64) -> Option<FramePrefix> {
+        let version = self.parsed.header.version;
+        let prefix = read_at(&mut self.input, offset, frame_prefix_size(version) as usize).ok()?;
+        FramePrefix::parse(&prefix, version)
+    }
+}
+
+/// Position du prochain marqueur de synchronisation à partir de `from`
+fn find_marker<R: Read + Seek>(input: &mut R, from: u64) -> io::Result<Option<u64>> {
+    input.seek(SeekFrom::Start(from))?;
+    let mut buffer = Vec::new();
+    let mut base = from;
+    loop {
+        // Un marqueur peut chevaucher deux morceaux
+        let consumed = buffer.len().saturating_sub(SYNC_MARKER.len() - 1);
+        buffer.drain(..consumed);
+        base += consumed as u64;
+
+        let read = input.by_ref().take(SCAN_CHUNK).read_to_end(&mut buffer)?;
+        if let Some(found) = buffer.windows(SYNC_MARKER.len()).position(|window| window == SYNC_MARKER) {
+            return Ok(Some(base + found as u64));
+        }
+        if read == 0 {
+            return Ok(None);
+        }
+    }
+}
+
+fn read_at<R: Read + Seek>(input: &mut R, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = vec![0u8; len];
+    input.seek(SeekFrom::Start(offset))?;
+    input.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Enregistre la plage perdue `[start, end)` et la remplit selon `fill`
+fn lose<W: Write>(output: &mut W, report: &mut SalvageReport, start: u64, end: u64, fill: GapFill) -> Result<()> {
+    if end <= start {
+        return Ok(());
+    }
+    report.lost_ranges.push(ByteRange { start, end });
+    if fill == GapFill::Zeros {
+        io::copy(&mut io::repeat(0).take(end - start), output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionOptions, KeyDerivation, SYNC_HEADER_SIZE};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_salvage_skips_damaged_blocks() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("salvage.bin");
+        let compressed_path = dir.path().join("salvage.ntk");
+        let output_path = dir.path().join("salvage_out.bin");
+
+        let mut test_data = vec![0u8; 8 * 1024];
+        getrandom::getrandom(&mut test_data)?;
+        fs::write(&input_path, &test_data)?;
+
+        let compressor = Compressor::new(CompressionOptions {
+            block_size: 1024,
+            use_encryption: true,
+            password: Some("salvage".into()),
+            key_derivation: KeyDerivation::Pbkdf2 { iterations: 1000 },
+            ..Default::default()
+        });
+        compressor.compress(&input_path, &compressed_path)?;
+        let archive = fs::read(&compressed_path)?;
+        let data_start = compressor.read_header(&mut &archive[..])?.raw.len();
+        let frame_offset = |index: usize| {
+            let mut offset = data_start;
+            for _ in 0..index {
+                let size = &archive[offset + SYNC_HEADER_SIZE..offset + SYNC_HEADER_SIZE + 4];
+                offset += SYNC_HEADER_SIZE + 4 + u32::from_le_bytes(size.try_into().unwrap()) as usize;
+            }
+            offset
+        };
+
+        // Bloc 2 altéré, marqueur du bloc 5 détruit et index perdu : reprise sur les marqueurs
+        let mut damaged = archive.clone();
+        damaged[frame_offset(2) + 40] ^= 0xFF;
+        damaged[frame_offset(5)..frame_offset(5) + 8].fill(0);
+        damaged.truncate(frame_offset(8) + SYNC_HEADER_SIZE + 4);
+        fs::write(&compressed_path, &damaged)?;
+        assert!(compressor.decompress(&compressed_path, &output_path).is_err());
+
+        let report = compressor.salvage(&compressed_path, &output_path, GapFill::Zeros)?;
+        assert_eq!(report.recovered_blocks, 6);
+        assert_eq!(report.lost_ranges, vec![
+            ByteRange { start: 2048, end: 3072 },
+            ByteRange { start: 5120, end: 6144 },
+        ]);
+        let mut expected = test_data.clone();
+        expected[2048..3072].fill(0);
+        expected[5120..6144].fill(0);
+        assert_eq!(fs::read(&output_path)?, expected);
+
+        // Sans remplissage, les blocs récupérés sont mis bout à bout
+        compressor.salvage(&compressed_path, &output_path, GapFill::Omit)?;
+        assert_eq!(fs::read(&output_path)?, [&test_data[..2048], &test_data[3072..5120], &test_data[6144..]].concat());
+
+        // Avec l'index intact, la perte est localisée de la même façon
+        let mut damaged = archive.clone();
+        damaged[frame_offset(3) + 40] ^= 0xFF;
+        fs::write(&compressed_path, &damaged)?;
+        let report = compressor.salvage(&compressed_path, &output_path, GapFill::Zeros)?;
+        assert_eq!(report.lost_ranges, vec![ByteRange { start: 3072, end: 4096 }]);
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+//! Stéganographie : archive cachée dans les bits de poids faible d'une image
+//!
+//! Les bits sont répartis sur les canaux R, G et B (1 ou 2 bits par canal), dans un ordre
+//! pseudo-aléatoire dérivé du mot de passe : sans lui, les emplacements utilisés ne se
+//! devinent pas. Sans mot de passe, l'ordre est mélangé de la même façon pour tous.
+//!
+//! Les données cachées forment un conteneur : en-tête (magique, version, bits par
+//! emplacement, taille, BLAKE3 des données) écrit à 1 bit par emplacement, puis l'archive.
+//! L'extraction distingue ainsi un support sans données d'un conteneur abîmé.
+//!
+//! Avec `use_steganography`, `compress` écrit directement l'image (l'archive reste en
+//! mémoire) et les lectures (`decompress`, `verify`…) reconnaissent une image en entrée.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use image::{ImageFormat, RgbaImage};
+
+use crate::output::{AtomicFile, OverwritePolicy};
+use crate::volume::ArchiveReader;
+use crate::{CompressionError, Compressor, SteganographyError};
+
+// Contexte de dérivation de la clé d'ordre des emplacements
+const ORDER_CONTEXT: &str = "ntk-ultra-compression steganography slot order v1";
+const FEISTEL_ROUNDS: usize = 4;
+
+const STEGO_MAGIC: &[u8; 4] = b"NTKS";
+const STEGO_VERSION: u8 = 1;
+// Magique, version, bits, taille (u32 LE), BLAKE3 des données
+pub(crate) const STEGO_HEADER_SIZE: usize = 4 + 1 + 1 + 4 + 32;
+// L'en-tête est toujours écrit à 1 bit par emplacement
+const STEGO_HEADER_SLOTS: u64 = STEGO_HEADER_SIZE as u64 * 8;
+
+/// Support de stéganographie : suite d'emplacements dont les bits de poids faible
+/// portent les données
+pub(crate) trait Cover {
+    fn slots(&self) -> u64;
+    fn low_byte(&self, slot: u64) -> u8;
+    fn set_low_byte(&mut self, slot: u64, value: u8);
+}
+
+/// Canaux R, G et B de chaque pixel ; l'alpha n'est pas modifié
+impl Cover for RgbaImage {
+    fn slots(&self) -> u64 {
+        self.width() as u64 * self.height() as u64 * 3
+    }
+
+    fn low_byte(&self, slot: u64) -> u8 {
+        let (x, y, channel) = locate(self, slot);
+        self.get_pixel(x, y)[channel]
+    }
+
+    fn set_low_byte(&mut self, slot: u64, value: u8) {
+        let (x, y, channel) = locate(self, slot);
+        self.get_pixel_mut(x, y)[channel] = value;
+    }
+}
+
+// Pixel (x, y) et canal d'un emplacement
+fn locate(image: &RgbaImage, slot: u64) -> (u32, u32, usize) {
+    let pixel = slot / 3;
+    let width = image.width() as u64;
+    ((pixel % width) as u32, (pixel / width) as u32, (slot % 3) as usize)
+}
+
+/// Permutation à clé de `0..n` (réseau de Feistel et « cycle walking ») : l'ordre
+/// d'écriture des emplacements, sans table en mémoire
+pub(crate) struct SlotOrder {
+    n: u64,
+    half_bits: u32,
+    keys: [u64; FEISTEL_ROUNDS],
+}
+
+impl SlotOrder {
+    pub(crate) fn new(password: Option<&str>, n: u64) -> Self {
+        let key = blake3::derive_key(ORDER_CONTEXT, password.unwrap_or_default().as_bytes());
+        let keys = std::array::from_fn(|i| u64::from_le_bytes(key[i * 8..i * 8 + 8].try_into().unwrap()));
+        // Plus petit domaine 2^(2h) contenant 0..n
+        let mut half_bits = 1;
+        while half_bits < 32 && (1u64 << (2 * half_bits)) < n {
+            half_bits += 1;
+        }
+        Self { n, half_bits, keys }
+    }
+
+    /// Emplacement de rang `index` (`index < n`)
+    pub(crate) fn slot(&self, index: u64) -> u64 {
+        let mut x = index;
+        loop {
+            x = self.permute(x);
+            if x < self.n {
+                return x;
+            }
+        }
+    }
+
+    fn permute(&self, x: u64) -> u64 {
+        let mask = (1u64 << self.half_bits) - 1;
+        let (mut left, mut right) = (x >> self.half_bits, x & mask);
+        for key in self.keys {
+            (left, right) = (right, left ^ (mix(right ^ key) & mask));
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+/// Finaliseur de splitmix64
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Écrit `data` à partir du rang `first`, `bits` bits par emplacement (bit de poids faible
+/// de chaque octet d'abord)
+pub(crate) fn write_bits<C: Cover>(cover: &mut C, order: &SlotOrder, first: u64, bits: u8, data: &[u8]) {
+    let mask = (1u8 << bits) - 1;
+    let mut stream = data.iter().flat_map(|&byte| (0..8).map(move |bit| (byte >> bit) & 1));
+    let mut index = first;
+    loop {
+        let mut value = 0u8;
+        let mut taken = 0;
+        for (bit, b) in stream.by_ref().take(bits as usize).enumerate() {
+            value |= b << bit;
+            taken += 1;
+        }
+        if taken == 0 {
+            return;
+        }
+        let slot = order.slot(index);
+        let current = cover.low_byte(slot);
+        // Un dernier emplacement incomplet garde ses bits supérieurs
+        let used = mask >> (bits as usize - taken);
+        cover.set_low_byte(slot, (current & !used) | value);
+        index += 1;
+    }
+}
+
+/// Lit `len` octets écrits par `write_bits`
+pub(crate) fn read_bits<C: Cover>(cover: &C, order: &SlotOrder, first: u64, bits: u8, len: usize) -> Vec<u8> {
+    let mut data = vec![0u8; len];
+    let total = len * 8;
+    let mut position = 0;
+    let mut index = first;
+    while position < total {
+        let value = cover.low_byte(order.slot(index));
+        for bit in 0..bits as usize {
+            if position < total {
+                data[position / 8] |= ((value >> bit) & 1) << (position % 8);
+                position += 1;
+            }
+        }
+        index += 1;
+    }
+    data
+}
+
+// Les erreurs restent des `CompressionError` (codes de sortie de la CLI)
+pub(crate) fn stego_error(e: SteganographyError) -> anyhow::Error {
+    CompressionError::SteganographyError(e).into()
+}
+
+/// Octets utilisables pour l'archive
+pub(crate) fn capacity<C: Cover>(cover: &C, bits: u8) -> u64 {
+    cover.slots().saturating_sub(STEGO_HEADER_SLOTS) * bits as u64 / 8
+}
+
+/// Écrit le conteneur (en-tête puis `data`) dans `cover`
+pub(crate) fn embed<C: Cover>(cover: &mut C, password: Option<&str>, bits: u8, data: &[u8]) -> Result<()> {
+    let max_bytes = capacity(cover, bits).min(u32::MAX as u64);
+    if data.len() as u64 > max_bytes {
+        return Err(stego_error(SteganographyError::TooLarge(max_bytes)));
+    }
+
+    let order = SlotOrder::new(password, cover.slots());
+    write_bits(cover, &order, 0, 1, &container_header(bits, data));
+    write_bits(cover, &order, STEGO_HEADER_SLOTS, bits, data);
+    Ok(())
+}
+
+/// Relit le conteneur écrit par `embed`
+pub(crate) fn extract<C: Cover>(cover: &C, password: Option<&str>) -> Result<Vec<u8>> {
+    let slots = cover.slots();
+    if slots < STEGO_HEADER_SLOTS {
+        return Err(stego_error(SteganographyError::NoHiddenData));
+    }
+    let order = SlotOrder::new(password, slots);
+    let header = read_bits(cover, &order, 0, 1, STEGO_HEADER_SIZE);
+    let (bits, size) = parse_container_header(&header)?;
+    if !(1..=2).contains(&bits) {
+        return Err(stego_error(SteganographyError::CorruptedData(format!("invalid bits per slot {}", bits))));
+    }
+    if size > capacity(cover, bits) {
+        return Err(stego_error(SteganographyError::CorruptedData(format!("size {} exceeds the cover capacity", size))));
+    }
+
+    let data = read_bits(cover, &order, STEGO_HEADER_SLOTS, bits, size as usize);
+    verify_container(&header, &data)?;
+    Ok(data)
+}
+
+/// En-tête du conteneur de `data` ; `bits` est le paramètre d'insertion propre au support
+pub(crate) fn container_header(bits: u8, data: &[u8]) -> Vec<u8> {
+    let mut header = Vec::with_capacity(STEGO_HEADER_SIZE);
+    header.extend_from_slice(STEGO_MAGIC);
+    header.push(STEGO_VERSION);
+    header.push(bits);
+    header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    header.extend_from_slice(blake3::hash(data).as_bytes());
+    header
+}
+
+/// Vérifie le magique et la version d'un en-tête lu ; renvoie le paramètre d'insertion et
+/// la taille des données
+pub(crate) fn parse_container_header(header: &[u8]) -> Result<(u8, u64)> {
+    if &header[..4] != STEGO_MAGIC {
+        return Err(stego_error(SteganographyError::NoHiddenData));
+    }
+    if header[4] != STEGO_VERSION {
+        return Err(stego_error(SteganographyError::UnsupportedVersion(header[4])));
+    }
+    Ok((header[5], u32::from_le_bytes(header[6..10].try_into().unwrap()) as u64))
+}
+
+/// Compare les données extraites à l'empreinte de l'en-tête
+pub(crate) fn verify_container(header: &[u8], data: &[u8]) -> Result<()> {
+    if blake3::hash(data).as_bytes() != &header[10..] {
+        return Err(stego_error(SteganographyError::CorruptedData("checksum mismatch".into())));
+    }
+    Ok(())
+}
+
+/// Archive en cours d'écriture, cachée dans l'image à la validation : elle n'est jamais
+/// écrite sur le disque hors de l'image
+pub(crate) struct ImageOutput {
+    cover: RgbaImage,
+    format: ImageFormat,
+    target: PathBuf,
+    policy: OverwritePolicy,
+    password: Option<String>,
+    bits: u8,
+    data: Vec<u8>,
+}
+
+impl ImageOutput {
+    fn capacity(&self) -> u64 {
+        capacity(&self.cover, self.bits).min(u32::MAX as u64)
+    }
+
+    /// Cache l'archive et écrit l'image ; renvoie le chemin réellement écrit
+    pub(crate) fn commit(mut self) -> Result<PathBuf> {
+        embed(&mut self.cover, self.password.as_deref(), self.bits, &self.data)?;
+        let mut output_file = AtomicFile::create(&self.target, self.policy)?;
+        self.cover.write_to(&mut output_file, self.format)
+            .map_err(|e| stego_error(SteganographyError::Cover(e.to_string())))?;
+        output_file.commit()
+    }
+}
+
+impl Write for ImageOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let capacity = self.capacity();
+        if (self.data.len() + buf.len()) as u64 > capacity {
+            return Err(io::Error::other(SteganographyError::TooLarge(capacity)));
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Format de l'image produite, déduit de l'extension : seul un format sans perte
+/// conserve les bits de poids faible
+fn lossless_format(path: &Path) -> Result<ImageFormat> {
+    let format = ImageFormat::from_path(path)
+        .map_err(|e| stego_error(SteganographyError::Cover(e.to_string())))?;
+    match format {
+        ImageFormat::Png | ImageFormat::Bmp | ImageFormat::Tiff => Ok(format),
+        format => Err(stego_error(SteganographyError::Cover(format!(
+            "output format {:?} is lossy or unsupported; use PNG, BMP or TIFF", format
+        )))),
+    }
+}
+
+impl Compressor {
+    /// Vérifie que `path` désigne une image sans perte (PNG, BMP ou TIFF), avant tout
+    /// travail de compression
+    pub fn check_image_output<P: AsRef<Path>>(path: P) -> Result<()> {
+        lossless_format(path.as_ref()).map(|_| ())
+    }
+
+    /// Sortie cachée dans l'image `image_path`, écrite vers `path` (format déduit de
+    /// l'extension)
+    pub(crate) fn image_output(&self, image_path: &Path, path: &Path) -> Result<ImageOutput> {
+        let format = lossless_format(path)?;
+        let cover = image::open(image_path)
+            .map_err(|e| stego_error(SteganographyError::Cover(e.to_string())))?
+            .to_rgba8();
+        Ok(ImageOutput {
+            cover,
+            format,
+            target: path.to_path_buf(),
+            policy: self.options.overwrite,
+            password: self.options.password.clone(),
+            bits: self.steganography_bits()?,
+            data: Vec::new(),
+        })
+    }
+
+    /// Archive cachée dans `path` si c'est une image reconnue, `None` sinon
+    pub(crate) fn hidden_archive(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let mut signature = Vec::new();
+        match File::open(path) {
+            Ok(file) => file.take(16).read_to_end(&mut signature)?,
+            // Jeu de volumes (`nom.ntk.001`…) : laissé à `ArchiveReader`
+            Err(_) => return Ok(None),
+        };
+        if image::guess_format(&signature).is_err() {
+            return Ok(None);
+        }
+        let image = image::open(path)
+            .map_err(|e| stego_error(SteganographyError::Cover(e.to_string())))?
+            .to_rgba8();
+        extract(&image, self.options.password.as_deref()).map(Some)
+    }
+
+    /// Bits de poids faible utilisés par canal (`steganography_bits`, 1 par défaut)
+    pub(crate) fn steganography_bits(&self) -> Result<u8> {
+        match self.options.steganography_bits {
+            0 | 1 => Ok(1),
+            2 => Ok(2),
+            _ => Err(stego_error(SteganographyError::Cover("steganography_bits must be 1 or 2".into()))),
+        }
+    }
+
+    /// Renvoie le chemin réellement écrit (voir `OverwritePolicy::Rename`)
+    pub fn hide_in_image<P: AsRef<Path>>(&self, archive_path: P, image_path: P, output_path: P) -> Result<PathBuf> {
+        let mut output = self.image_output(image_path.as_ref(), output_path.as_ref())?;
+        io::copy(&mut ArchiveReader::open(archive_path.as_ref())?, &mut output)?;
+        output.commit()
+    }
+
+    /// Les bits par emplacement sont lus dans le conteneur ; seul le mot de passe doit
+    /// correspondre. Renvoie le chemin réellement écrit (voir `OverwritePolicy::Rename`)
+    pub fn extract_from_image<P: AsRef<Path>>(&self, image_path: P, output_path: P) -> Result<PathBuf> {
+        let image = image::open(image_path)
+            .map_err(|e| stego_error(SteganographyError::Cover(e.to_string())))?
+            .to_rgba8();
+        let archive_data = extract(&image, self.options.password.as_deref())?;
+
+        let mut output_file = AtomicFile::create(output_path.as_ref(), self.options.overwrite)?;
+        output_file.write_all(&archive_data)?;
+        output_file.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionOptions;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_keyed_multichannel_embedding() -> Result<()> {
+        let dir = tempdir()?;
+        let cover = dir.path().join("cover.png");
+        let stego = dir.path().join("stego.png");
+        let archive = dir.path().join("hidden.ntk");
+        let extracted = dir.path().join("extracted.ntk");
+
+        // 64 x 64 pixels : 1494 octets avec 1 bit par canal, 2988 avec 2
+        RgbaImage::from_fn(64, 64, |x, y| image::Rgba([x as u8 * 4, y as u8 * 4, 128, 255])).save(&cover)?;
+        let mut data = vec![0u8; 2000];
+        getrandom::getrandom(&mut data)?;
+        fs::write(&archive, &data)?;
+
+        let options = |bits, password: &str| CompressionOptions {
+            steganography_bits: bits,
+            password: Some(password.into()),
+            overwrite: crate::OverwritePolicy::Overwrite,
+            ..Default::default()
+        };
+        assert!(Compressor::new(options(1, "key")).hide_in_image(&archive, &cover, &stego).is_err());
+
+        let compressor = Compressor::new(options(2, "key"));
+        compressor.hide_in_image(&archive, &cover, &stego)?;
+        compressor.extract_from_image(&stego, &extracted)?;
+        assert_eq!(fs::read(&extracted)?, data);
+
+        // Autre mot de passe : autres emplacements, pas de conteneur
+        let err = Compressor::new(options(2, "other")).extract_from_image(&stego, &extracted).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CompressionError>(),
+            Some(CompressionError::SteganographyError(SteganographyError::NoHiddenData))
+        ));
+
+        // Les bits modifiés sont répartis sur toute l'image et sur les trois canaux
+        let (before, after) = (image::open(&cover)?.to_rgba8(), image::open(&stego)?.to_rgba8());
+        let changed: Vec<_> = before.pixels().zip(after.pixels()).enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .collect();
+        assert!(changed.iter().any(|(i, _)| *i > 64 * 60));
+        for channel in 0..3 {
+            assert!(changed.iter().any(|(_, (a, b))| a[channel] != b[channel]));
+        }
+        assert!(changed.iter().all(|(_, (_, b))| b[3] == 255));
+        Ok(())
+    }
+
+    #[test]
+    fn test_container_detects_missing_and_corrupted_data() -> Result<()> {
+        let kind = |result: Result<Vec<u8>>| match result.unwrap_err().downcast::<CompressionError>() {
+            Ok(CompressionError::SteganographyError(e)) => e,
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        // Image ordinaire : aucune donnée cachée
+        let mut image = RgbaImage::from_fn(32, 32, |x, y| image::Rgba([(x * y) as u8, x as u8, y as u8, 255]));
+        assert!(matches!(kind(extract(&image, None)), SteganographyError::NoHiddenData));
+
+        let data = b"archive NTK".repeat(20);
+        embed(&mut image, None, 1, &data)?;
+        assert_eq!(extract(&image, None)?, data);
+
+        // Un bit de données inversé : conteneur reconnu mais abîmé
+        let slot = SlotOrder::new(None, image.slots()).slot(STEGO_HEADER_SLOTS + 17);
+        image.set_low_byte(slot, image.low_byte(slot) ^ 1);
+        assert!(matches!(kind(extract(&image, None)), SteganographyError::CorruptedData(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_into_image_and_decompress() -> Result<()> {
+        let dir = tempdir()?;
+        let cover = dir.path().join("cover.png");
+        let input = dir.path().join("notes.txt");
+        let stego = dir.path().join("stego.png");
+        let output = dir.path().join("notes.out");
+        RgbaImage::from_fn(128, 128, |x, y| image::Rgba([x as u8, y as u8, (x ^ y) as u8, 255])).save(&cover)?;
+        let content = b"rapport de terrain, ".repeat(500);
+        fs::write(&input, &content)?;
+
+        let options = CompressionOptions {
+            use_encryption: true,
+            password: Some("secret".into()),
+            use_steganography: true,
+            steganography_image: Some(cover.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let metadata = Compressor::new(options.clone()).compress(&input, &stego)?;
+        assert_eq!(metadata.output_path.as_deref(), Some(stego.to_string_lossy().as_ref()));
+
+        // Seules l'image d'origine, l'entrée et l'image produite sont sur le disque
+        let mut names: Vec<_> = fs::read_dir(dir.path())?.map(|e| e.unwrap().file_name()).collect();
+        names.sort();
+        assert_eq!(names, ["cover.png", "notes.txt", "stego.png"]);
+
+        // Un format avec perte effacerait les bits cachés
+        let lossy = dir.path().join("stego.jpg");
+        let err = Compressor::new(options.clone()).compress(&input, &lossy).unwrap_err();
+        assert!(err.to_string().contains("Jpeg"));
+        assert!(!lossy.exists());
+
+        let reader = Compressor::new(CompressionOptions { use_steganography: false, ..options });
+        reader.decompress(&stego, &output)?;
+        assert_eq!(fs::read(&output)?, content);
+        reader.verify(&stego)?;
+        assert!(Compressor::new(CompressionOptions::default()).decompress(&stego, &output).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_image_output_rejects_overflow() -> Result<()> {
+        let dir = tempdir()?;
+        let cover = dir.path().join("cover.png");
+        let stego = dir.path().join("stego.png");
+        RgbaImage::from_pixel(16, 16, image::Rgba([90, 90, 90, 255])).save(&cover)?;
+
+        let compressor = Compressor::new(CompressionOptions::default());
+        let mut output = compressor.image_output(&cover, &stego)?;
+        let capacity = output.capacity() as usize;
+        output.write_all(&vec![7u8; capacity])?;
+        let err = output.write(&[7]).unwrap_err();
+        let inner = err.get_ref().and_then(|e| e.downcast_ref::<SteganographyError>());
+        assert!(matches!(inner, Some(SteganographyError::TooLarge(max)) if *max == capacity as u64));
+        assert_eq!(output.data.len(), capacity);
+        drop(output);
+        assert!(!stego.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,504 @@
+//! Adaptateurs `Write` / `Read` pour le format NTK
+//!
+//! `NtkWriter` produit une archive au fil de l'eau, sans connaître la taille des données
+//! ni pouvoir revenir en arrière dans la sortie ; `NtkReader` la relit de la même façon.
+//! Un bloc n'est scellé qu'une fois le suivant entamé, pour savoir s'il est le dernier.
+
+use std::io::{self, Read, Write};
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::{
+    block_aad, block_nonce, frame_prefix_size, from_io_error, into_io_error, read_frame_prefix, truncation_error,
+    Chunking, CompressionError, Compressor, FramePrefix, FrameWriter, ParityInfo, ParsedHeader, FOOTER_MAGIC,
+    FOOTER_NONCE_INDEX, MAX_FOOTER_SIZE, TRAILER_SIZE, VERSION_AUTHENTICATED, VERSION_BLOCK_INDEX,
+    VERSION_CHECKSUMS, VERSION_PARITY_RECORD,
+};
+use crate::parity::{record_ending_at, scan_records};
+
+// Lecture de la fin d'archive par morceaux, pour traverser la section de parité
+const TAIL_CHUNK: u64 = 1024 * 1024;
+
+/// Trame lue, pas encore déchiffrée ni décompressée
+pub(crate) struct Frame {
+    pub(crate) index: u32,
+    /// Position de la trame, relative au début des données
+    pub(crate) offset: u64,
+    pub(crate) is_final: bool,
+    pub(crate) data: Vec<u8>,
+    /// Index et position d'origine annoncés par le préfixe (v11+)
+    sync: Option<(u32, u64)>,
+}
+
+/// Lecture séquentielle des trames d'une archive, sans retour en arrière ; le décodage
+/// des trames (`Compressor::open_frame`) peut se faire en parallèle
+pub(crate) struct BlockDecoder {
+    parsed: ParsedHeader,
+    key: Vec<u8>,
+    next: Option<FramePrefix>,
+    index: u32,
+    /// Position de la prochaine trame, relative au début des données
+    offset: u64,
+    /// Taille des blocs acceptés, soit la position d'origine du suivant
+    data_position: u64,
+    final_block_seen: bool,
+    /// Empreintes calculées, comparées à l'index en fin d'archive (v9+)
+    hasher: blake3::Hasher,
+    block_hashes: Vec<blake3::Hash>,
+}
+
+impl BlockDecoder {
+    /// `input` doit être positionné juste après l'en-tête ; `key` : clé de l'archive
+    pub(crate) fn new<R: Read>(parsed: ParsedHeader, key: Vec<u8>, input: &mut R) -> Result<Self> {
+        let mut decoder = Self {
+            parsed,
+            key,
+            next: None,
+            index: 0,
+            offset: 0,
+            data_position: 0,
+            final_block_seen: false,
+            hasher: blake3::Hasher::new(),
+            block_hashes: Vec::new(),
+        };
+        decoder.next = decoder.read_prefix(input)?;
+        Ok(decoder)
+    }
+
+    /// Décode le bloc suivant ; `None` une fois le dernier bloc lu et l'archive vérifiée
+    pub(crate) fn next_block<R: Read>(&mut self, compressor: &Compressor, input: &mut R) -> Result<Option<Vec<u8>>> {
+        match self.next_frame(compressor, input)? {
+            Some(frame) => {
+                let decompressed = compressor.open_frame(&self.parsed, &self.key, &frame)?;
+                self.accept(&frame, &decompressed)?;
+                Ok(Some(decompressed))
+            }
+            None => {
+                self.finish(compressor, input)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Lit la trame suivante sans la décoder ; `None` à la fin des blocs
+    pub(crate) fn next_frame<R: Read>(&mut self, compressor: &Compressor, input: &mut R) -> Result<Option<Frame>> {
+        let authenticated = self.authenticated();
+        let indexed = self.parsed.header.version >= VERSION_BLOCK_INDEX;
+
+        let (block_size, sync) = match self.next {
+            Some(prefix) if !(indexed && prefix.size == 0) => (prefix.size, prefix.sync),
+            _ => return Ok(None),
+        };
+        compressor.check_cancelled()?;
+
+        // Vérification de sécurité sur la taille du bloc
+        if block_size == 0 || block_size as usize > compressor.max_frame_size(&self.parsed.header) {
+            return Err(CompressionError::InvalidFormat.into());
+        }
+
+        // Lire le bloc
+        let mut data = vec![0u8; block_size as usize];
+        if let Err(e) = input.read_exact(&mut data) {
+            if authenticated && e.kind() == io::ErrorKind::UnexpectedEof {
+                return Err(truncation_error(e, authenticated));
+            }
+            // Erreur du format remontée par le lecteur (fragment irréparable)
+            if e.get_ref().is_some_and(|inner| inner.is::<CompressionError>()) {
+                return Err(from_io_error(e));
+            }
+            return Err(CompressionError::IoError(io::Error::new(
+                e.kind(),
+                format!("Failed to read block at offset {}: {}", self.offset, e)
+            )).into());
+        }
+
+        // Le dernier bloc doit porter le marqueur de fin : il est suivi de la trame
+        // de fin (archives indexées) ou de la fin du fichier (anciennes versions)
+        self.next = self.read_prefix(input)?;
+        let is_final = match self.next {
+            Some(prefix) => indexed && prefix.size == 0,
+            None => !indexed,
+        };
+
+        let frame = Frame { index: self.index, offset: self.offset, is_final, data, sync };
+        self.offset += block_size as u64 + frame_prefix_size(self.parsed.header.version);
+        self.index = self.index.wrapping_add(1);
+        self.final_block_seen = is_final;
+        Ok(Some(frame))
+    }
+
+    /// Prend en compte un bloc décodé, dans l'ordre de l'archive
+    pub(crate) fn accept(&mut self, frame: &Frame, decompressed: &[u8]) -> Result<()> {
+        check_sync(frame.sync, frame.index, self.data_position)?;
+        self.data_position += decompressed.len() as u64;
+        if self.checksums() {
+            self.hasher.update(decompressed);
+            self.block_hashes.push(blake3::hash(decompressed));
+        }
+        Ok(())
+    }
+
+    /// Bloc déjà accepté, restitué à nouveau (déduplication)
+    pub(crate) fn accept_reference(&mut self, data: &[u8]) {
+        self.data_position += data.len() as u64;
+        if self.checksums() {
+            self.hasher.update(data);
+        }
+    }
+
+    /// Contrôles de fin d'archive, une fois tous les blocs acceptés
+    pub(crate) fn finish<R: Read>(&mut self, compressor: &Compressor, input: &mut R) -> Result<()> {
+        // Une archive authentifiée sans bloc final a été tronquée
+        if self.authenticated() && !self.final_block_seen {
+            return Err(CompressionError::AuthenticationError("archive is truncated".into()).into());
+        }
+        let Some(terminator) = self.next.take().filter(|prefix| prefix.size == 0) else {
+            return Ok(());
+        };
+        check_sync(terminator.sync, self.index, self.data_position)?;
+        if self.checksums() {
+            self.verify_checksums(compressor, input)?;
+        }
+        Ok(())
+    }
+
+    /// Lit l'index qui suit la trame de fin (avant ou après la parité) et le compare aux
+    /// empreintes calculées
+    fn verify_checksums<R: Read>(&mut self, compressor: &Compressor, input: &mut R) -> Result<()> {
+        // Seuls le début (index qui précède la parité, v14+) et la fin sont conservés : la
+        // section de parité est ignorée
+        let keep = (MAX_FOOTER_SIZE + TRAILER_SIZE) as usize;
+        let mut head = Vec::new();
+        let mut tail = Vec::new();
+        let mut total = 0u64;
+        loop {
+            let start = tail.len();
+            let read = input.by_ref().take(TAIL_CHUNK).read_to_end(&mut tail)?;
+            if read == 0 {
+                break;
+            }
+            if head.len() < keep {
+                let len = read.min(keep - head.len());
+                head.extend_from_slice(&tail[start..start + len]);
+            }
+            total += read as u64;
+            if tail.len() > keep {
+                tail.drain(..tail.len() - keep);
+            }
+        }
+
+        let Some(trailer) = tail.len().checked_sub(TRAILER_SIZE as usize) else {
+            return Err(compressor.missing_footer_error(&self.parsed));
+        };
+        let stored_len = u64::from_le_bytes(tail[trailer..trailer + 8].try_into().unwrap());
+        if &tail[trailer + 8..] != FOOTER_MAGIC || stored_len > trailer as u64 {
+            return Err(compressor.missing_footer_error(&self.parsed));
+        }
+        // v14+ : copie de l'enregistrement qui précède le trailer, sinon celle qui suit l'index
+        let protected = match self.parsed.header.version >= VERSION_PARITY_RECORD {
+            true => match record_ending_at(&mut io::Cursor::new(&tail), trailer as u64)? {
+                Some(parity) => Some(parity),
+                None => scan_records(&mut io::Cursor::new(&head), stored_len, head.len() as u64, |parity, end| {
+                    parity.footer_len() == stored_len && end == stored_len + parity.record_len()
+                })?,
+            },
+            false => None,
+        };
+
+        // Après la trame de fin : l'index puis la parité entre ses deux enregistrements
+        // (v14+), ou la parité puis l'index
+        let footer = match protected {
+            Some(_) => head.get(..stored_len as usize).map(<[u8]>::to_vec),
+            None => Some(tail[trailer - stored_len as usize..trailer].to_vec()),
+        };
+        let Some(footer) = footer else {
+            return Err(compressor.missing_footer_error(&self.parsed));
+        };
+        let mut footer = compressor.open_footer(&self.parsed, &self.key, footer)?;
+        let parity_section = match protected {
+            Some(parity) => {
+                let len = parity.parity_len() + 2 * parity.record_len();
+                footer.parity = Some(parity);
+                len
+            }
+            None => footer.parity.as_ref().map_or(0, ParityInfo::parity_len),
+        };
+        if total != stored_len + parity_section + TRAILER_SIZE {
+            return Err(compressor.missing_footer_error(&self.parsed));
+        }
+        if footer.blocks.len() != self.block_hashes.len() {
+            return Err(CompressionError::IntegrityError("block index does not match the archive".into()).into());
+        }
+        for (index, hash) in self.block_hashes.iter().enumerate() {
+            footer.verify_block(index, hash)?;
+        }
+        footer.verify_checksum(&self.hasher.finalize())
+    }
+
+    fn checksums(&self) -> bool {
+        self.parsed.header.version >= VERSION_CHECKSUMS
+    }
+
+    fn authenticated(&self) -> bool {
+        self.parsed.header.encrypted && self.parsed.header.version >= VERSION_AUTHENTICATED
+    }
+
+    // Les archives indexées se terminent par une trame de taille 0 : la fin est explicite
+    fn read_prefix<R: Read>(&self, input: &mut R) -> Result<Option<FramePrefix>> {
+        let indexed = self.parsed.header.version >= VERSION_BLOCK_INDEX;
+        read_frame_prefix(input, &self.parsed.header, indexed)
+    }
+}
+
+/// Le préfixe d'une trame (v11+) doit annoncer sa place réelle dans l'archive
+fn check_sync(sync: Option<(u32, u64)>, index: u32, data_position: u64) -> Result<()> {
+    match sync {
+        Some(sync) if sync != (index, data_position) => Err(CompressionError::IntegrityError(
+            format!("frame {} does not match its position in the archive", index)
+        ).into()),
+        _ => Ok(()),
+    }
+}
+
+/// Compresse (et chiffre) au format NTK tout ce qui y est écrit ;
+/// `finish` doit être appelé pour écrire le dernier bloc et l'index
+pub struct NtkWriter<W: Write> {
+    compressor: Compressor,
+    frames: FrameWriter<W>,
+    header_bytes: Vec<u8>,
+    key: Vec<u8>,
+    nonce_prefix: Vec<u8>,
+    block_size: usize,
+    buffer: Vec<u8>,
+    processed_bytes: u64,
+    start: Instant,
+}
+
+impl<W: Write> NtkWriter<W> {
+    /// Écrit l'en-tête ; la taille d'origine, inconnue, y est enregistrée à 0 (l'index
+    /// donne la taille réelle)
+    pub fn new(compressor: Compressor, mut inner: W, name: &str) -> Result<Self> {
+        // Écriture au fil de l'eau : blocs de taille fixe, une seule sortie, pas de base
+        let options = &compressor.options;
+        let unsupported = [
+            (options.chunking == Chunking::ContentDefined, "content-defined chunking"),
+            (options.base_archive.is_some(), "base archives"),
+            (options.volume_size > 0, "volumes"),
+            (options.use_steganography, "steganography"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(enabled, _)| *enabled) {
+            return Err(CompressionError::CompressionError(format!("{} cannot be used with NtkWriter", option)).into());
+        }
+
+        let (key, salt, nonce_prefix) = if compressor.options.use_encryption {
+            compressor.prepare_encryption()?
+        } else {
+            (vec![], vec![], vec![])
+        };
+
+        let header = compressor.new_header(name, 0)?;
+        let header_bytes = compressor.encode_header(&header, &salt, &nonce_prefix)?;
+        inner.write_all(&header_bytes)?;
+
+        let block_size = compressor.options.block_size.max(1);
+        Ok(Self {
+            frames: FrameWriter::new(inner, header_bytes.len() as u64, compressor.options.parity_percent)?,
+            header_bytes,
+            key,
+            nonce_prefix,
+            block_size,
+            buffer: Vec::with_capacity(block_size),
+            processed_bytes: 0,
+            start: Instant::now(),
+            compressor,
+        })
+    }
+
+    /// Scelle le dernier bloc, écrit l'index des blocs et renvoie le flux sous-jacent
+    pub fn finish(mut self) -> Result<W> {
+        self.write_block(true)?;
+        let mut footer = self.frames.end_blocks()?;
+        footer.original_size = Some(self.processed_bytes);
+        let footer = self.compressor.seal_footer(&footer, &self.header_bytes, &self.key, &self.nonce_prefix)?;
+        self.frames.finish(&footer)?;
+        Ok(self.frames.into_inner())
+    }
+
+    fn write_block(&mut self, is_final: bool) -> Result<()> {
+        self.compressor.check_cancelled()?;
+        let index = self.frames.index.len();
+        // L'index du bloc fait partie du nonce : il doit tenir sur 32 bits
+        if index >= FOOTER_NONCE_INDEX as usize {
+            return Err(CompressionError::CompressionError("Too many blocks for this block size".into()).into());
+        }
+
+        let nonce = block_nonce(&self.nonce_prefix, index as u32);
+        let aad = block_aad(&self.header_bytes, index as u32, is_final);
+        let block = self.compressor.compress_block(&self.buffer, &self.key, &nonce, &aad)?;
+        self.frames.write_block(&block, &self.buffer)?;
+
+        self.processed_bytes += self.buffer.len() as u64;
+        self.buffer.clear();
+        // Taille totale inconnue : seule la quantité traitée est significative
+        self.compressor.report_progress(self.processed_bytes, self.processed_bytes, self.start);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for NtkWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            // Le bloc plein n'est scellé que maintenant : il n'est pas le dernier
+            if self.buffer.len() == self.block_size {
+                self.write_block(false).map_err(into_io_error)?;
+            }
+            let len = (self.block_size - self.buffer.len()).min(buf.len() - written);
+            self.buffer.extend_from_slice(&buf[written..written + len]);
+            written += len;
+        }
+        Ok(buf.len())
+    }
+
+    /// Le bloc en cours reste en mémoire : il ne peut être scellé avant de savoir s'il est le dernier
+    fn flush(&mut self) -> io::Result<()> {
+        self.frames.flush()
+    }
+}
+
+/// Décompresse (et déchiffre) une archive NTK lue séquentiellement ;
+/// pour une archive multi-fichiers, produit le flux brut (table des matières puis contenu)
+pub struct NtkReader<R: Read> {
+    compressor: Compressor,
+    inner: R,
+    decoder: BlockDecoder,
+    block: Vec<u8>,
+    position: usize,
+    processed_bytes: u64,
+    start: Instant,
+}
+
+impl<R: Read> NtkReader<R> {
+    /// Lit et vérifie l'en-tête
+    pub fn new(compressor: Compressor, mut inner: R) -> Result<Self> {
+        let parsed = compressor.read_header(&mut inner)?;
+        if parsed.header.deduplicated {
+            return Err(CompressionError::CompressionError(
+                "deduplicated archives cannot be read as a stream".into()
+            ).into());
+        }
+        let key = compressor.archive_key(&parsed)?;
+        let decoder = BlockDecoder::new(parsed, key, &mut inner)?;
+        Ok(Self {
+            compressor,
+            inner,
+            decoder,
+            block: Vec::new(),
+            position: 0,
+            processed_bytes: 0,
+            start: Instant::now(),
+        })
+    }
+
+    /// Nom du fichier d'origine enregistré dans l'en-tête
+    pub fn original_name(&self) -> &str {
+        &self.decoder.parsed.header.original_name
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for NtkReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.block.len() {
+            match self.decoder.next_block(&self.compressor, &mut self.inner).map_err(into_io_error)? {
+                Some(block) => {
+                    self.block = block;
+                    self.position = 0;
+                    self.processed_bytes += self.block.len() as u64;
+                    let total_bytes = self.decoder.parsed.header.original_size;
+                    self.compressor.report_progress(self.processed_bytes, total_bytes, self.start);
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.block.len() - self.position);
+        buf[..len].copy_from_slice(&self.block[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompressionOptions;
+    use std::io::Cursor;
+
+    fn stream_compressor(use_encryption: bool) -> Compressor {
+        Compressor::new(CompressionOptions {
+            block_size: 1024,
+            use_encryption,
+            password: Some("test123".to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_stream_roundtrip() -> Result<()> {
+        let test_data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+
+        for use_encryption in [false, true] {
+            let mut writer = NtkWriter::new(stream_compressor(use_encryption), Vec::new(), "stream.bin")?;
+            // Écritures de tailles irrégulières, à cheval sur les blocs
+            for chunk in test_data.chunks(700) {
+                writer.write_all(chunk)?;
+            }
+            let archive = writer.finish()?;
+
+            let mut reader = NtkReader::new(stream_compressor(use_encryption), Cursor::new(&archive))?;
+            assert_eq!(reader.original_name(), "stream.bin");
+            let mut decoded = Vec::new();
+            reader.read_to_end(&mut decoded)?;
+            assert_eq!(decoded, test_data);
+
+            // L'archive produite est lisible par l'API fichier
+            let dir = tempfile::tempdir()?;
+            let archive_path = dir.path().join("stream.ntk");
+            std::fs::write(&archive_path, &archive)?;
+            let compressor = stream_compressor(use_encryption);
+            assert_eq!(compressor.read_range(&archive_path, 1000, 2000)?, &test_data[1000..3000]);
+            // Taille réelle lue dans l'index, pas le 0 de l'en-tête
+            assert_eq!(compressor.get_metadata(&archive_path)?.original_size, test_data.len() as u64);
+        }
+
+        // Options que l'écriture en flux ne peut pas respecter : refusées
+        let chunked = Compressor::new(CompressionOptions { chunking: Chunking::ContentDefined, ..Default::default() });
+        assert!(NtkWriter::new(chunked, Vec::new(), "stream.bin").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_reader_reports_tampering() -> Result<()> {
+        // Exactement deux blocs pleins : le second est le dernier
+        let mut writer = NtkWriter::new(stream_compressor(true), Vec::new(), "stream.bin")?;
+        writer.write_all(&[7u8; 2048])?;
+        let mut archive = writer.finish()?;
+        assert!(NtkReader::new(stream_compressor(true), &archive[..])?.read_to_end(&mut Vec::new()).is_ok());
+
+        // Altérer le premier bloc, juste après l'en-tête et le préfixe de longueur
+        archive[crate::HEADER_SIZE + crate::SALT_SIZE + crate::NONCE_PREFIX_SIZE + crate::SYNC_HEADER_SIZE + 8] ^= 1;
+        let mut reader = NtkReader::new(stream_compressor(true), &archive[..])?;
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.get_ref().and_then(|e| e.downcast_ref::<CompressionError>()),
+            Some(CompressionError::AuthenticationError(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,478 @@
+//! Archives découpées en volumes de taille fixe
+//!
+//! `nom.ntk` devient `nom.ntk.001`, `nom.ntk.002`… Chaque volume commence par un en-tête
+//! (identifiant du jeu, numéro, nombre de volumes) qui permet de signaler un volume
+//! manquant, étranger ou mal numéroté ; les données des volumes mises bout à bout
+//! forment l'archive.
+
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::output::{already_exists, candidates, commit_all, AtomicFile, OverwritePolicy, PendingFile};
+use crate::stego::ImageOutput;
+use crate::CompressionError;
+
+const VOLUME_MAGIC: &[u8] = b"NTKV";
+const SET_ID_SIZE: usize = 16;
+// Magic + identifiant du jeu + numéro (u32 LE, à partir de 1) + nombre de volumes (u32 LE)
+const VOLUME_HEADER_SIZE: u64 = 28;
+const COUNT_OFFSET: u64 = 24;
+const MIN_VOLUME_SIZE: u64 = 4096;
+
+/// Chemin du volume `number` (à partir de 1) : `nom.ntk.001`…
+pub(crate) fn volume_path(base: &Path, number: u32) -> PathBuf {
+    let mut name = base.as_os_str().to_owned();
+    name.push(format!(".{:03}", number));
+    PathBuf::from(name)
+}
+
+/// Sortie d'une archive : un fichier, un jeu de volumes ou une image (stéganographie)
+pub(crate) enum ArchiveOutput {
+    File(AtomicFile),
+    Volumes(VolumeWriter),
+    Image(ImageOutput),
+}
+
+impl ArchiveOutput {
+    /// `volume_size` : taille maximale d'un volume (0 : un seul fichier)
+    pub(crate) fn create(path: &Path, policy: OverwritePolicy, volume_size: u64) -> Result<Self> {
+        match volume_size {
+            0 => Ok(Self::File(AtomicFile::create(path, policy)?)),
+            size => Ok(Self::Volumes(VolumeWriter::new(path, policy, size)?)),
+        }
+    }
+
+    /// Valide la sortie ; renvoie le chemin écrit (premier volume pour un jeu)
+    pub(crate) fn commit(self) -> Result<PathBuf> {
+        match self {
+            Self::File(file) => file.commit(),
+            Self::Volumes(volumes) => volumes.commit(),
+            Self::Image(image) => image.commit(),
+        }
+    }
+}
+
+impl Write for ArchiveOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.write(buf),
+            Self::Volumes(volumes) => volumes.write(buf),
+            Self::Image(image) => image.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.flush(),
+            Self::Volumes(volumes) => volumes.flush(),
+            Self::Image(image) => image.flush(),
+        }
+    }
+}
+
+/// Écrit un jeu de volumes ; aucun n'est visible avant `commit`, qui les valide tous ou
+/// aucun
+pub(crate) struct VolumeWriter {
+    base: PathBuf,
+    policy: OverwritePolicy,
+    volume_size: u64,
+    set_id: [u8; SET_ID_SIZE],
+    /// Volumes complets, fermés jusqu'à `commit`
+    finished: Vec<PendingFile>,
+    current: AtomicFile,
+    /// Données écrites dans le dernier volume, en-tête compris
+    current_len: u64,
+    /// Volumes du jeu remplacé (`Overwrite`) et leur numéro
+    replaced: Vec<(u32, PathBuf)>,
+}
+
+impl VolumeWriter {
+    fn new(path: &Path, policy: OverwritePolicy, volume_size: u64) -> Result<Self> {
+        if volume_size < MIN_VOLUME_SIZE {
+            return Err(CompressionError::CompressionError(
+                format!("Volume size must be at least {} bytes", MIN_VOLUME_SIZE)
+            ).into());
+        }
+        let mut set_id = [0u8; SET_ID_SIZE];
+        getrandom::getrandom(&mut set_id)
+            .map_err(|e| CompressionError::IoError(io::Error::other(e.to_string())))?;
+
+        // Le nombre de volumes n'est pas connu d'avance : tous les noms `base.NNN` doivent
+        // être libres, ou (`Overwrite`) appartenir à un même jeu, remplacé en entier
+        let base = match policy {
+            OverwritePolicy::Rename => candidates(path)
+                .find(|base| existing_volumes(base).is_ok_and(|volumes| volumes.is_empty()))
+                .ok_or_else(|| already_exists(&volume_path(path, 1)))?,
+            _ => path.to_path_buf(),
+        };
+        let existing = existing_volumes(&base)?;
+        let (policy, replaced) = match (policy, existing.first()) {
+            (OverwritePolicy::Overwrite, _) => {
+                check_same_set(&existing)?;
+                (policy, existing)
+            }
+            (_, Some((_, first))) => return Err(already_exists(first)),
+            (_, None) => (OverwritePolicy::Fail, existing),
+        };
+
+        let current = create_volume(&base, policy, &set_id, 1)?;
+        Ok(Self {
+            base,
+            policy,
+            volume_size,
+            set_id,
+            finished: Vec::new(),
+            current,
+            current_len: VOLUME_HEADER_SIZE,
+            replaced,
+        })
+    }
+
+    fn next_volume(&mut self) -> io::Result<()> {
+        let number = self.finished.len() as u32 + 2;
+        let volume = create_volume(&self.base, self.policy, &self.set_id, number).map_err(crate::into_io_error)?;
+        let finished = std::mem::replace(&mut self.current, volume);
+        self.finished.push(finished.finish().map_err(crate::into_io_error)?);
+        self.current_len = VOLUME_HEADER_SIZE;
+        Ok(())
+    }
+
+    fn commit(self) -> Result<PathBuf> {
+        let mut volumes = self.finished;
+        volumes.push(self.current.finish()?);
+        let count = volumes.len() as u32;
+        for volume in &volumes {
+            volume.patch(COUNT_OFFSET, &count.to_le_bytes())?;
+        }
+        let mut paths = commit_all(volumes)?;
+
+        // Volumes en trop du jeu remplacé
+        for (number, path) in &self.replaced {
+            if *number > count {
+                let _ = fs::remove_file(path);
+            }
+        }
+        Ok(paths.swap_remove(0))
+    }
+}
+
+fn create_volume(base: &Path, policy: OverwritePolicy, set_id: &[u8], number: u32) -> Result<AtomicFile> {
+    let mut volume = AtomicFile::create(&volume_path(base, number), policy)?;
+    volume.write_all(VOLUME_MAGIC)?;
+    volume.write_all(set_id)?;
+    volume.write_all(&number.to_le_bytes())?;
+    // Nombre de volumes, connu une fois l'archive écrite
+    volume.write_all(&0u32.to_le_bytes())?;
+    Ok(volume)
+}
+
+/// Fichiers nommés comme un volume de `base` (`base.001`, `base.002`…), triés par numéro
+fn existing_volumes(base: &Path) -> Result<Vec<(u32, PathBuf)>> {
+    let dir = match base.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let prefix = format!("{}.", base.file_name().unwrap_or_default().to_string_lossy());
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut volumes = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let number = name.strip_prefix(&prefix)
+            .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<u32>().ok());
+        if let Some(number) = number {
+            volumes.push((number, entry.path()));
+        }
+    }
+    volumes.sort();
+    Ok(volumes)
+}
+
+/// Vérifie que les fichiers à remplacer sont tous des volumes d'un même jeu
+fn check_same_set(volumes: &[(u32, PathBuf)]) -> Result<()> {
+    let mut expected = None;
+    for (_, path) in volumes {
+        let mut header = [0u8; VOLUME_HEADER_SIZE as usize];
+        let is_volume = File::open(path).and_then(|mut file| file.read_exact(&mut header)).is_ok()
+            && &header[..4] == VOLUME_MAGIC;
+        if !is_volume || *expected.get_or_insert(header[4..20].to_vec()) != header[4..20] {
+            return Err(volume_error(format!(
+                "{} does not belong to the volume set being replaced", path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.current_len == self.volume_size {
+            self.next_volume()?;
+        }
+        let len = buf.len().min((self.volume_size - self.current_len) as usize);
+        let written = self.current.write(&buf[..len])?;
+        self.current_len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.current.flush()
+    }
+}
+
+/// Archive ouverte en lecture : un fichier, un jeu de volumes lu comme un seul flux, ou
+/// une archive extraite d'une image (stéganographie)
+pub(crate) enum ArchiveReader {
+    File(File),
+    Volumes(VolumeReader),
+    Memory(Cursor<Vec<u8>>),
+}
+
+impl ArchiveReader {
+    /// Ouvre `path`, ou le jeu de volumes correspondant : `nom.ntk` absent mais
+    /// `nom.ntk.001` présent, ou n'importe quel volume du jeu
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        if !path.exists() && volume_path(path, 1).exists() {
+            return Ok(Self::Volumes(VolumeReader::open(path)?));
+        }
+        let mut file = File::open(path)?;
+        let mut magic = [0u8; 4];
+        let is_volume = file.read_exact(&mut magic).is_ok() && magic == VOLUME_MAGIC;
+        file.seek(SeekFrom::Start(0))?;
+        if !is_volume {
+            return Ok(Self::File(file));
+        }
+        // Un volume quelconque : le jeu est relu depuis le premier
+        let base = match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension.bytes().all(|b| b.is_ascii_digit()) => path.with_extension(""),
+            _ => return Err(volume_error(format!("{} is a volume but is not named like one (name.ntk.001)", path.display()))),
+        };
+        Ok(Self::Volumes(VolumeReader::open(&base)?))
+    }
+}
+
+impl Read for ArchiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.read(buf),
+            Self::Volumes(volumes) => volumes.read(buf),
+            Self::Memory(data) => data.read(buf),
+        }
+    }
+}
+
+impl Seek for ArchiveReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::File(file) => file.seek(pos),
+            Self::Volumes(volumes) => volumes.seek(pos),
+            Self::Memory(data) => data.seek(pos),
+        }
+    }
+}
+
+/// Jeu de volumes vérifié : chaque volume est présent, du même jeu et à sa place
+pub(crate) struct VolumeReader {
+    /// Volume, position de ses données dans l'archive et taille de ses données
+    volumes: Vec<(File, u64, u64)>,
+    position: u64,
+    len: u64,
+}
+
+impl VolumeReader {
+    fn open(base: &Path) -> Result<Self> {
+        let mut volumes = Vec::new();
+        let mut expected: Option<([u8; SET_ID_SIZE], u32)> = None;
+        let mut len = 0u64;
+        let mut number = 1u32;
+
+        loop {
+            let path = volume_path(base, number);
+            let mut file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    let count = expected.map_or(String::from("?"), |(_, count)| count.to_string());
+                    return Err(CompressionError::IoError(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Missing volume {} (volume {} of {})", path.display(), number, count)
+                    )).into());
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut header = [0u8; VOLUME_HEADER_SIZE as usize];
+            if file.read_exact(&mut header).is_err() || &header[..4] != VOLUME_MAGIC {
+                return Err(volume_error(format!("{} is not an NTK volume", path.display())));
+            }
+            let set_id: [u8; SET_ID_SIZE] = header[4..20].try_into().unwrap();
+            let found = u32::from_le_bytes(header[20..24].try_into().unwrap());
+            let count = u32::from_le_bytes(header[24..28].try_into().unwrap());
+
+            if count == 0 || found > count {
+                return Err(volume_error(format!("{} belongs to an incomplete volume set", path.display())));
+            }
+            let (expected_set, expected_count) = *expected.get_or_insert((set_id, count));
+            if set_id != expected_set || count != expected_count {
+                return Err(volume_error(format!("{} belongs to another archive", path.display())));
+            }
+            if found != number {
+                return Err(volume_error(format!(
+                    "{} is volume {} of the set, expected volume {} (volumes renamed or out of order)",
+                    path.display(), found, number
+                )));
+            }
+
+            let data_len = file.metadata()?.len() - VOLUME_HEADER_SIZE;
+            // Tous les volumes sauf le dernier ont la même taille
+            if let Some(&(_, _, first_len)) = volumes.first() {
+                if number < count && data_len != first_len || number == count && data_len > first_len {
+                    return Err(volume_error(format!("{} is truncated or has the wrong size", path.display())));
+                }
+            }
+            volumes.push((file, len, data_len));
+            len += data_len;
+
+            if number == count {
+                return Ok(Self { volumes, position: 0, len });
+            }
+            number += 1;
+        }
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let position = self.position;
+        let Some((file, start, len)) = self.volumes.iter_mut()
+            .find(|(_, start, len)| (*start..*start + *len).contains(&position)) else {
+            return Ok(0);
+        };
+        file.seek(SeekFrom::Start(VOLUME_HEADER_SIZE + position - *start))?;
+        let available = buf.len().min((*start + *len - position) as usize);
+        let read = file.read(&mut buf[..available])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        Ok(self.position)
+    }
+}
+
+fn volume_error(message: String) -> anyhow::Error {
+    CompressionError::IntegrityError(message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompressionOptions, Compressor};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_volumes_roundtrip_and_errors() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("volumes.bin");
+        let archive_path = dir.path().join("volumes.ntk");
+        let output_path = dir.path().join("volumes_out.bin");
+
+        let mut test_data = vec![0u8; 20 * 1024];
+        getrandom::getrandom(&mut test_data)?;
+        fs::write(&input_path, &test_data)?;
+
+        let compressor = Compressor::new(CompressionOptions {
+            block_size: 4096,
+            volume_size: MIN_VOLUME_SIZE,
+            ..Default::default()
+        });
+        let metadata = compressor.compress(&input_path, &archive_path)?;
+        assert_eq!(metadata.output_path, Some(volume_path(&archive_path, 1).to_string_lossy().into_owned()));
+        assert!(!archive_path.exists());
+        assert!(volume_path(&archive_path, 6).exists());
+        assert!(fs::metadata(volume_path(&archive_path, 1))?.len() <= MIN_VOLUME_SIZE);
+
+        // Le jeu s'ouvre par son nom ou par n'importe lequel de ses volumes
+        for path in [archive_path.clone(), volume_path(&archive_path, 3)] {
+            compressor.decompress(&path, &output_path)?;
+            assert_eq!(fs::read(&output_path)?, test_data);
+        }
+        assert_eq!(compressor.get_metadata(&archive_path)?.compressed_size, metadata.compressed_size);
+        assert_eq!(compressor.read_range(&archive_path, 10_000, 100)?, &test_data[10_000..10_100]);
+
+        let assert_error = |expected: &str| {
+            let err = compressor.decompress(&archive_path, &output_path).unwrap_err().to_string();
+            assert!(err.contains(expected), "unexpected error: {}", err);
+        };
+
+        // Volumes intervertis
+        let (second, third) = (volume_path(&archive_path, 2), volume_path(&archive_path, 3));
+        let swapped = dir.path().join("swap");
+        fs::rename(&second, &swapped)?;
+        fs::rename(&third, &second)?;
+        fs::rename(&swapped, &third)?;
+        assert_error("is volume 3 of the set, expected volume 2");
+
+        // Volume 2 manquant, le 3 remis à sa place
+        fs::remove_file(&third)?;
+        fs::rename(&second, &third)?;
+        assert_error("Missing volume");
+        Ok(())
+    }
+
+    #[test]
+    fn test_volume_set_names_are_checked_up_front() -> Result<()> {
+        let dir = tempdir()?;
+        let input_path = dir.path().join("volumes.bin");
+        let archive_path = dir.path().join("volumes.ntk");
+        let mut test_data = vec![0u8; 20 * 1024];
+        getrandom::getrandom(&mut test_data)?;
+        fs::write(&input_path, &test_data)?;
+        let compressor = |overwrite| Compressor::new(CompressionOptions {
+            block_size: 4096,
+            volume_size: MIN_VOLUME_SIZE,
+            overwrite,
+            ..Default::default()
+        });
+
+        // Un fichier étranger à la place du volume 3 : rien n'est écrit, rien n'est écrasé
+        let foreign = volume_path(&archive_path, 3);
+        fs::write(&foreign, b"other")?;
+        for policy in [OverwritePolicy::Fail, OverwritePolicy::Overwrite] {
+            assert!(compressor(policy).compress(&input_path, &archive_path).is_err());
+        }
+        assert!(!volume_path(&archive_path, 1).exists());
+        assert_eq!(fs::read(&foreign)?, b"other");
+
+        // `Rename` choisit un nom dont aucun volume n'existe
+        let renamed = compressor(OverwritePolicy::Rename).compress(&input_path, &archive_path)?;
+        let renamed_base = dir.path().join("volumes (1).ntk");
+        assert_eq!(renamed.output_path, Some(volume_path(&renamed_base, 1).to_string_lossy().into_owned()));
+
+        // Un jeu plus court remplace le précédent en entier
+        fs::write(&input_path, &test_data[..6 * 1024])?;
+        compressor(OverwritePolicy::Overwrite).compress(&input_path, &renamed_base)?;
+        assert!(!volume_path(&renamed_base, 6).exists());
+        let output_path = dir.path().join("out.bin");
+        compressor(OverwritePolicy::Overwrite).decompress(&renamed_base, &output_path)?;
+        assert_eq!(fs::read(&output_path)?, &test_data[..6 * 1024]);
+        assert!(!fs::read_dir(dir.path())?.any(|e| e.unwrap().file_name().to_string_lossy().starts_with('.')));
+        Ok(())
+    }
+}
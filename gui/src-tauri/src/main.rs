@@ -3,7 +3,7 @@
     windows_subsystem = "windows"
 )]
 
-use ntk_core::{Compressor, CompressionOptions, FileMetadata};
+use ntk_core::{CancellationToken, Compressor, CompressionOptions, FileMetadata};
 use std::sync::{Arc, Mutex};
 use tauri::State;
 use serde::{Deserialize, Serialize};
@@ -23,6 +23,12 @@ pub struct DecompressRequest {
     password: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct VerifyRequest {
+    input_path: String,
+    password: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SteganographyHideRequest {
     archive_path: String,
@@ -47,9 +53,28 @@ pub struct ProgressEvent {
 
 struct ProgressState(Arc<Mutex<Option<Box<dyn Fn(ProgressEvent) + Send>>>>);
 
+/// Jeton d'annulation de l'opération en cours
+struct JobState(Mutex<Option<CancellationToken>>);
+
+impl JobState {
+    /// Associe un nouveau jeton au compresseur de l'opération qui démarre
+    fn start(&self, compressor: &mut Compressor) {
+        let token = CancellationToken::new();
+        compressor.set_cancellation_token(token.clone());
+        if let Ok(mut job) = self.0.lock() {
+            *job = Some(token);
+        }
+    }
+}
+
 #[tauri::command]
-async fn compress(request: CompressionRequest, progress: State<'_, ProgressState>) -> Result<FileMetadata, String> {
+async fn compress(
+    request: CompressionRequest,
+    progress: State<'_, ProgressState>,
+    job: State<'_, JobState>,
+) -> Result<FileMetadata, String> {
     let mut compressor = Compressor::new(request.options);
+    job.start(&mut compressor);
     
     // Configurer le callback de progression
     let progress_state = Arc::clone(&progress.0);
@@ -74,12 +99,17 @@ async fn compress(request: CompressionRequest, progress: State<'_, ProgressState
 }
 
 #[tauri::command]
-async fn decompress(request: DecompressRequest, progress: State<'_, ProgressState>) -> Result<(), String> {
+async fn decompress(
+    request: DecompressRequest,
+    progress: State<'_, ProgressState>,
+    job: State<'_, JobState>,
+) -> Result<String, String> {
     let mut compressor = Compressor::new(CompressionOptions {
         use_encryption: request.password.is_some(),
         password: request.password,
         ..Default::default()
     });
+    job.start(&mut compressor);
 
     // Configurer le callback de progression
     let progress_state = Arc::clone(&progress.0);
@@ -100,9 +130,33 @@ async fn decompress(request: DecompressRequest, progress: State<'_, ProgressStat
 
     compressor
         .decompress(request.input_path, request.output_path)
+        .map(|path| path.to_string_lossy().into_owned())
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn verify(request: VerifyRequest, job: State<'_, JobState>) -> Result<(), String> {
+    let mut compressor = Compressor::new(CompressionOptions {
+        use_encryption: request.password.is_some(),
+        password: request.password,
+        ..Default::default()
+    });
+    job.start(&mut compressor);
+    compressor
+        .verify(request.input_path)
+        .map_err(|e| e.to_string())
+}
+
+/// Annule l'opération en cours : elle s'arrête au bloc suivant et supprime sa sortie partielle
+#[tauri::command]
+async fn cancel_job(job: State<'_, JobState>) -> Result<(), String> {
+    let job = job.0.lock().map_err(|e| e.to_string())?;
+    if let Some(token) = job.as_ref() {
+        token.cancel();
+    }
+    Ok(())
+}
+
 #[tauri::command]
 async fn get_metadata(path: String) -> Result<FileMetadata, String> {
     let compressor = Compressor::new(CompressionOptions::default());
@@ -133,27 +187,32 @@ async fn clear_progress_handler(progress: State<'_, ProgressState>) -> Result<()
 }
 
 #[tauri::command]
-async fn hide_in_image(request: SteganographyHideRequest) -> Result<(), String> {
+async fn hide_in_image(request: SteganographyHideRequest) -> Result<String, String> {
     let compressor = Compressor::new(CompressionOptions::default());
     compressor
         .hide_in_image(request.archive_path, request.image_path, request.output_path)
+        .map(|path| path.to_string_lossy().into_owned())
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn extract_from_image(request: SteganographyExtractRequest) -> Result<(), String> {
+async fn extract_from_image(request: SteganographyExtractRequest) -> Result<String, String> {
     let compressor = Compressor::new(CompressionOptions::default());
     compressor
         .extract_from_image(request.image_path, request.output_path)
+        .map(|path| path.to_string_lossy().into_owned())
         .map_err(|e| e.to_string())
 }
 
 fn main() {
     tauri::Builder::default()
         .manage(ProgressState(Arc::new(Mutex::new(None))))
+        .manage(JobState(Mutex::new(None)))
         .invoke_handler(tauri::generate_handler![
             compress,
             decompress,
+            verify,
+            cancel_job,
             get_metadata,
             set_progress_handler,
             clear_progress_handler,
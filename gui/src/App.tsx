@@ -91,6 +91,8 @@ function App() {
   const [isProcessing, setIsProcessing] = useState(false);
   const [metadata, setMetadata] = useState<FileMetadata | null>(null);
   const [error, setError] = useState<string | null>(null);
+  // Chemin réellement écrit (peut différer de la cible si le fichier existait déjà)
+  const [writtenPath, setWrittenPath] = useState<string | null>(null);
   const [isCompressed, setIsCompressed] = useState(false);
   const [progress, setProgress] = useState<ProgressEvent | null>(null);
   const [showPasswordDialog, setShowPasswordDialog] = useState(false);
@@ -194,7 +196,7 @@ function App() {
         level: compressionLevel,
         threads: navigator.hardwareConcurrency || 4,
         block_size: 16 * 1024 * 1024,
-        dictionary_size: 64 * 1024 * 1024,
+        dictionary_size: 112 * 1024,
         use_encryption: useEncryption,
         password: useEncryption ? password : undefined,
         use_steganography: false,
@@ -247,10 +249,11 @@ function App() {
   const startDecompression = async (input: string, output: string, pwd: string | null) => {
     setIsProcessing(true);
     setError(null);
+    setWrittenPath(null);
     setProgress(null);
 
     try {
-      await invoke<void>('decompress', {
+      const written = await invoke<string>('decompress', {
         request: {
           input_path: input,
           output_path: output,
@@ -260,6 +263,7 @@ function App() {
 
       setMetadata(null);
       setError(null);
+      setWrittenPath(written);
       setShowPasswordDialog(false);
       setTempDecompressRequest(null);
       setPassword('');
@@ -271,6 +275,14 @@ function App() {
     }
   };
 
+  const handleCancel = async () => {
+    try {
+      await invoke('cancel_job');
+    } catch (e) {
+      handleError(e);
+    }
+  };
+
   const formatSize = (bytes: number) => {
     const units = ['B', 'KB', 'MB', 'GB', 'TB'];
     let size = bytes;
@@ -302,13 +314,15 @@ function App() {
     try {
       setIsProcessing(true);
       setError(null);
-      await invoke('hide_in_image', {
+      setWrittenPath(null);
+      const written = await invoke<string>('hide_in_image', {
         request: {
           archive_path: inputPath,
           image_path: steganographyImage,
           output_path: steganographyOutput,
         },
       });
+      setWrittenPath(written);
       setIsProcessing(false);
     } catch (e) {
       setError(e as string);
@@ -320,12 +334,14 @@ function App() {
     try {
       setIsProcessing(true);
       setError(null);
-      await invoke('extract_from_image', {
+      setWrittenPath(null);
+      const written = await invoke<string>('extract_from_image', {
         request: {
           image_path: steganographyImage,
           output_path: outputPath,
         },
       });
+      setWrittenPath(written);
       setIsProcessing(false);
     } catch (e) {
       setError(e as string);
@@ -362,6 +378,12 @@ function App() {
           </Alert>
         )}
 
+        {writtenPath && (
+          <Alert severity="success" sx={{ mb: 3 }} onClose={() => setWrittenPath(null)}>
+            Fichier écrit : {writtenPath}
+          </Alert>
+        )}
+
         {currentTab === 0 && (
           <>
             <Paper sx={{ p: 3, mb: 3 }}>
@@ -492,6 +514,15 @@ function App() {
                   >
                     {Math.round(progress.percent)}%
                   </Typography>
+                  <Button
+                    variant="outlined"
+                    color="error"
+                    onClick={handleCancel}
+                    sx={{ mt: 1 }}
+                    fullWidth
+                  >
+                    Cancel
+                  </Button>
                 </Box>
               )}
 